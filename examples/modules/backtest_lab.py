@@ -6,6 +6,7 @@ import py_backtest_lab
 class BacktestLab:
     def __init__(self, database):
         self.database = database
+        self.backtester = py_backtest_lab.Backtester()
 
     def _convert_rust_price_ohlc(price):
         return {
@@ -93,27 +94,27 @@ class BacktestLab:
             else:
                 prices_by_symbol[symbol] = BacktestLab._convert_rust_prices_ohlc(symbol_prices)
         if type_tick:
-            py_backtest_lab.prices_tick_add(prices_by_symbol)
+            self.backtester.prices_tick_add(prices_by_symbol)
         else:
-            py_backtest_lab.prices_ohlc_add(prices_by_symbol)
+            self.backtester.prices_ohlc_add(prices_by_symbol)
 
     def backtest_signals(self, conditions, strategy_rules, signals):
         signals = self._filter_signals(signals)
         signals = BacktestLab._convert_rust_signals(signals)
-        return py_backtest_lab.backtest_signals(conditions, strategy_rules, signals)
+        return self.backtester.backtest_signals(conditions, strategy_rules, signals)
     
     def signal_check_filter(self, strategy_rules, signal):
         signal_rust = BacktestLab._convert_rust_signal(signal)
-        return py_backtest_lab.signal_check_filter(strategy_rules, signal_rust)
+        return self.backtester.signal_check_filter(strategy_rules, signal_rust)
 
     def signal_check_entry(self, strategy_rules, signal_result):
-        return py_backtest_lab.signal_check_entry(strategy_rules, signal_result)
+        return self.backtester.signal_check_entry(strategy_rules, signal_result)
     
     def signal_check_exit(self, strategy_rules, signal_result):
-        return py_backtest_lab.signal_check_exit(strategy_rules, signal_result)
+        return self.backtester.signal_check_exit(strategy_rules, signal_result)
     
     def algo_mean(self, symbol, type_tick, type_ohlc, index_from, index_to, minutes):
-        return py_backtest_lab.algo_mean(symbol, type_tick, type_ohlc, index_from, index_to, minutes)
+        return self.backtester.algo_mean(symbol, type_tick, type_ohlc, index_from, index_to, minutes)
     
     def algo_std_dev(self, symbol, type_tick, type_ohlc, index_from, index_to, minutes, mean):
-        return py_backtest_lab.algo_std_dev(symbol, type_tick, type_ohlc, index_from, index_to, minutes, mean)
\ No newline at end of file
+        return self.backtester.algo_std_dev(symbol, type_tick, type_ohlc, index_from, index_to, minutes, mean)
\ No newline at end of file
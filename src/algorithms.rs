@@ -1,48 +1,46 @@
-use crate::{evaluation::signal::Signal, prices::price::{Ohlc, Price, PriceType, Tick}};
+use crate::{evaluation::signal::SignalResult, prices::{price::{Ohlc, Price, PriceType, Tick}, price_slice::PriceSlice}};
 
 pub struct Algorithms;
 
 impl Algorithms {
-    pub fn check_stop_loss_hit(signal: &Signal, price: &dyn Price) -> bool {
-        if signal.action == "buy" && price.get(&(Tick::Bid, Ohlc::Low)) <= signal.stop_loss {
-            return true;
-        } else if signal.action == "sell" && price.get(&(Tick::Ask, Ohlc::High)) >= signal.stop_loss {
-            return true;
-        }
-        false
+    pub fn check_stop_loss_hit(signal_result: &SignalResult, price: &impl Price) -> bool {
+        let stop_loss = signal_result.stop_loss();
+        (signal_result.signal.action == "buy" && price.get(&(Tick::Bid, Ohlc::Low)) <= stop_loss)
+            || (signal_result.signal.action == "sell" && price.get(&(Tick::Ask, Ohlc::High)) >= stop_loss)
     }
 
-    pub fn calculate_volatility(price_type: &PriceType, prices: &[Box<dyn Price>], period_minutes: f32) -> f32 {
-        let mut windows = Vec::new();
-        let mut rolling_window = Vec::new();
-        let time_stamp_first = prices.last().unwrap().ts();
+    pub fn calculate_volatility(price_type: &PriceType, prices: &PriceSlice, period_minutes: f32) -> f32 {
+        let time_stamps = prices.time_stamps();
+        let values = prices.column(price_type);
+        let mut windows_sum = 0.0;
+        let mut windows_count = 0;
+        let mut window_min = f32::NAN;
+        let mut window_max = f32::NAN;
+        let time_stamp_first = *time_stamps.last().unwrap();
         let mut last_insert = time_stamp_first;
-        
-        for price in prices.iter().rev() {
-            if (time_stamp_first as i128 - price.ts() as i128) / 1000 / 60 > period_minutes as i128{
+
+        for (&time_stamp, &value) in time_stamps.iter().zip(values).rev() {
+            if (time_stamp_first as i128 - time_stamp as i128) / 1000 / 60 > period_minutes as i128 {
                 break;
             }
-            
-            rolling_window.push(price.get(&price_type));
-            
-            if (last_insert as i128 - price.ts() as i128) / 1000 / 60 > 5 {
-                windows.push(rolling_window.clone());
-                rolling_window.clear();
-                last_insert = price.ts();
+
+            window_min = window_min.min(value);
+            window_max = window_max.max(value);
+
+            if (last_insert as i128 - time_stamp as i128) / 1000 / 60 > 5 {
+                windows_sum += (window_max - window_min) / window_min;
+                windows_count += 1;
+                window_min = f32::NAN;
+                window_max = f32::NAN;
+                last_insert = time_stamp;
             }
         }
 
-        let mut windows_avg = 0.0;
-        if !windows.is_empty() {
-            for window in &windows {
-                let min_price = window.iter().cloned().fold(f32::NAN, f32::min);
-                let max_price = window.iter().cloned().fold(f32::NAN, f32::max);
-                windows_avg += (max_price - min_price) / min_price;
-            }
-            windows_avg /= windows.len() as f32;
+        if windows_count > 0 {
+            windows_sum / windows_count as f32
+        } else {
+            0.0
         }
-        
-        windows_avg
     }
 
     pub fn calculate_volatility_factor(action: &str, volatiliy: f32, strategy_factor: f32) -> f32 {
@@ -50,35 +48,65 @@ impl Algorithms {
         1.0 + action_factor * strategy_factor * volatiliy
     }
 
-    pub fn calculate_mean(price_type: &PriceType, prices: &[Box<dyn Price>], minutes: f32) -> f32 {
-        let mut rolling_window = Vec::new();
-        let time_stamp_first = prices.last().unwrap().ts();
-        
-        for price in prices.iter().rev() {
-            if (time_stamp_first as i128 - price.ts() as i128) / 1000 / 60 > minutes as i128{
+    pub fn calculate_mean(price_type: &PriceType, prices: &PriceSlice, minutes: f32) -> f32 {
+        let time_stamps = prices.time_stamps();
+        let values = prices.column(price_type);
+        let time_stamp_first = *time_stamps.last().unwrap();
+        let mut sum = 0.0;
+        let mut num_prices = 0;
+
+        for (&time_stamp, &value) in time_stamps.iter().zip(values).rev() {
+            if (time_stamp_first as i128 - time_stamp as i128) / 1000 / 60 > minutes as i128 {
                 break;
             }
-            rolling_window.push(price.get(&price_type));
+            sum += value;
+            num_prices += 1;
         }
 
-        rolling_window.iter().sum::<f32>() / rolling_window.len() as f32
+        sum / num_prices as f32
     }
 
-    pub fn calculate_standard_deviation(price_type: &PriceType, prices: &[Box<dyn Price>], minutes: f32, mean: f32) -> f32 {
+    pub fn calculate_standard_deviation(price_type: &PriceType, prices: &PriceSlice, minutes: f32, mean: f32) -> f32 {
+        let time_stamps = prices.time_stamps();
+        let values = prices.column(price_type);
         let mut deviation_sum = 0.0;
-        let time_stamp_first = prices.last().unwrap().ts();
+        let time_stamp_first = *time_stamps.last().unwrap();
         let mut num_prices = 0;
-    
-        for price in prices.iter().rev() {
-            if (time_stamp_first as i128 - price.ts() as i128) / 1000 / 60 > minutes as i128{
+
+        for (&time_stamp, &value) in time_stamps.iter().zip(values).rev() {
+            if (time_stamp_first as i128 - time_stamp as i128) / 1000 / 60 > minutes as i128 {
                 break;
             }
-            let price_value = price.get(&price_type);
-            deviation_sum += (price_value - mean).powi(2);
+            deviation_sum += (value - mean).powi(2);
             num_prices += 1;
         }
-    
-        let variance = deviation_sum / (num_prices as f32); 
+
+        let variance = deviation_sum / (num_prices as f32);
         variance.sqrt()
     }
-}
\ No newline at end of file
+
+    /// Mean true range of the bid prices within the last `period_minutes`, each range reaching back to the close before it.
+    pub fn calculate_average_true_range(prices: &PriceSlice, period_minutes: f32) -> f32 {
+        let time_stamps = prices.time_stamps();
+        let highs = prices.column(&(Tick::Bid, Ohlc::High));
+        let lows = prices.column(&(Tick::Bid, Ohlc::Low));
+        let closes = prices.column(&(Tick::Bid, Ohlc::Close));
+        let time_stamp_first = *time_stamps.last().unwrap();
+        let mut sum = 0.0;
+        let mut num_prices = 0;
+
+        for index in (1..time_stamps.len()).rev() {
+            if (time_stamp_first as i128 - time_stamps[index] as i128) / 1000 / 60 > period_minutes as i128 {
+                break;
+            }
+            sum += highs[index].max(closes[index - 1]) - lows[index].min(closes[index - 1]);
+            num_prices += 1;
+        }
+
+        if num_prices > 0 {
+            sum / num_prices as f32
+        } else {
+            0.0
+        }
+    }
+}
@@ -0,0 +1,392 @@
+// pyo3 0.20 expands #[pymethods] into impls inside anonymous consts, which newer toolchains flag.
+#![allow(non_local_definitions)]
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+
+use crate::error::BacktestError;
+use crate::evaluation::account::Account;
+use crate::evaluation::backtest_conditions::{BacktestConditions, ConditionRules};
+use crate::evaluation::backtest_result::BacktestResult;
+use crate::evaluation::backtesting_signals::BacktestingSignals;
+use crate::evaluation::engine_settings::EngineSettings;
+use crate::evaluation::replay_state::{ReplayContext, ReplayState};
+use crate::evaluation::session_event::SessionEvent;
+use crate::evaluation::signal::{Signal, SignalResult};
+use crate::evaluation::signal_update::SignalUpdate;
+use crate::prices::price::Price;
+use crate::prices::price_ohlc::PriceOhlc;
+use crate::prices::price_series::{PriceKind, PriceSeries};
+use crate::prices::price_slice::PriceSlice;
+use crate::prices::price_tick::PriceTick;
+use crate::strategies::strategy::{Strategy, StrategyManager, StrategyRules};
+
+/// What the replays of a session read, kept apart from their states so that both can be borrowed at the same time.
+struct SessionContext {
+    conditions: BacktestConditions,
+    rules: ConditionRules,
+    strategies: HashMap<String, (Strategy, EngineSettings)>,
+    prices: HashMap<String, PriceSeries>,
+}
+
+impl SessionContext {
+    fn replay_context<'a>(&'a self, source: &'a str, symbol: &'a str) -> Result<ReplayContext<'a>, BacktestError> {
+        let (strategy, settings) = self.strategies.get(source)
+            .ok_or_else(|| BacktestError::Internal(format!("Strategy of source '{}' not set", source)))?;
+        Ok(ReplayContext {
+            conditions: &self.conditions,
+            calendar: &self.rules.calendar,
+            slippage: &self.rules.slippage,
+            swap: &self.rules.swap,
+            intrabar: &self.rules.intrabar,
+            intrabar_prices: self.intrabar_prices(symbol),
+            strategy,
+            settings,
+            source,
+            symbol,
+            contract_size: self.conditions.contract_size(symbol)?,
+            allocation: 1.0,
+        })
+    }
+
+    /// Prices pushed for the symbol the prices of `symbol` are drilled down into.
+    fn intrabar_prices(&self, symbol: &str) -> Option<PriceSlice<'_>> {
+        self.rules.intrabar.drill_down_symbol(symbol)
+            .and_then(|symbol| self.prices.get(symbol))
+            .map(|series| series.as_slice())
+    }
+}
+
+/// Incremental backtest fed with prices and signals in time order, e.g. from a live feed or a step by step replay.
+/// A price is evaluated once the following price of its symbol arrives, so events lag one price behind.
+/// Pushed prices are kept for the strategies to look back on, only the last `history_ms` of them if it is set.
+#[pyclass]
+pub struct BacktestSession {
+    context: SessionContext,
+    accounts: HashMap<String, Account>,
+    history_ms: Option<u64>,
+    states: HashMap<String, HashMap<String, ReplayState>>,
+    signals_by_id: HashMap<String, Signal>,
+    tickets_opened: HashMap<u32, usize>,
+    ticket_next: u32,
+    finished: bool,
+}
+
+#[pymethods]
+impl BacktestSession {
+    #[new]
+    #[pyo3(signature = (conditions, strategy_rules, history_minutes=None))]
+    fn new(conditions: BacktestConditions, strategy_rules: HashMap<String, StrategyRules>, history_minutes: Option<f32>) -> PyResult<Self> {
+        let rules = BacktestingSignals::prepare_conditions(&conditions)?;
+        if history_minutes.is_some_and(|minutes| !(minutes > 0.0 && minutes.is_finite())) {
+            return Err(BacktestError::Argument("history_minutes has to be greater than 0".to_string()).into());
+        }
+        let mut strategies = HashMap::new();
+        let mut accounts = HashMap::new();
+        for (source, rules) in strategy_rules.iter() {
+            let strategy = StrategyManager::convert_rules_to_strategy(rules)?;
+            let settings = EngineSettings::new(&conditions, &strategy, 1);
+            strategies.insert(source.clone(), (strategy, settings));
+            accounts.insert(source.clone(), Account::new(&conditions));
+        }
+        Ok(BacktestSession {
+            context: SessionContext {
+                conditions,
+                rules,
+                strategies,
+                prices: HashMap::new(),
+            },
+            accounts,
+            history_ms: history_minutes.map(|minutes| (minutes * 60.0 * 1000.0) as u64),
+            states: HashMap::new(),
+            signals_by_id: HashMap::new(),
+            tickets_opened: HashMap::new(),
+            ticket_next: 0,
+            finished: false,
+        })
+    }
+
+    /// Queues a signal and returns its ticket, which identifies the signal in all events.
+    fn signal_push(&mut self, signal: Signal) -> PyResult<u32> {
+        self.check_running()?;
+        signal.validate()?;
+        self.context.conditions.contract_size(&signal.symbol)?;
+        if !self.context.strategies.contains_key(&signal.source) {
+            return Err(BacktestError::StrategyConfig(format!("Missing strategy rules for source '{}'", signal.source)).into());
+        }
+        if let Some(id) = &signal.id {
+            if self.signals_by_id.contains_key(id) {
+                return Err(BacktestError::Signal(format!("Signal id '{}' is not unique", id)).into());
+            }
+            self.signals_by_id.insert(id.clone(), signal.clone());
+        }
+
+        let ticket = self.ticket_next;
+        self.ticket_next += 1;
+        let state = self.states.entry(signal.symbol.clone()).or_default().entry(signal.source.clone()).or_default();
+        let mut signal_result = SignalResult::new(signal);
+        signal_result.ticket = Some(ticket);
+        let index = state.signals_pending.partition_point(|pending| pending.signal.time_stamp <= signal_result.signal.time_stamp);
+        state.signals_pending.insert(index, signal_result);
+        Ok(ticket)
+    }
+
+    /// Queues an update of a signal pushed before, it applies like the updates of `Backtester.backtest_signals`.
+    fn signal_update_push(&mut self, update: SignalUpdate) -> PyResult<()> {
+        self.check_running()?;
+        update.kind()?;
+        let signal = self.signals_by_id.get(&update.signal_id)
+            .ok_or_else(|| BacktestError::Signal(format!("Update of unknown signal '{}'", update.signal_id)))?;
+        if update.time_stamp < signal.time_stamp {
+            return Err(BacktestError::Signal(format!("Update of signal '{}' is older than the signal", update.signal_id)).into());
+        }
+        let state = self.states.get_mut(&signal.symbol).and_then(|states| states.get_mut(&signal.source))
+            .ok_or_else(|| BacktestError::Internal(format!("State of signal '{}' not set", update.signal_id)))?;
+        let index = state.updates_pending.partition_point(|pending| pending.time_stamp <= update.time_stamp);
+        state.updates_pending.insert(index, update);
+        Ok(())
+    }
+
+    fn price_tick_push(&mut self, symbol: String, price: PriceTick) -> PyResult<Vec<SessionEvent>> {
+        Ok(self.price_push(symbol, PriceKind::Tick, price)?)
+    }
+
+    fn price_ohlc_push(&mut self, symbol: String, mut price: PriceOhlc) -> PyResult<Vec<SessionEvent>> {
+        price.validate().map_err(BacktestError::PriceData)?;
+        if let Some(spread) = self.context.conditions.spreads.as_ref().and_then(|spreads| spreads.get(&symbol)) {
+            price.apply_spread(spread);
+        }
+        Ok(self.price_push(symbol, PriceKind::Ohlc, price)?)
+    }
+
+    /// Ends the session at the last price of every symbol, closing open positions and ignoring signals without entry.
+    fn finish(&mut self) -> PyResult<Vec<SessionEvent>> {
+        self.check_running()?;
+        self.finished = true;
+        let mut events = Vec::new();
+        for (symbol, states) in self.states.iter_mut() {
+            let prices = self.context.prices.get(symbol).map(|series| series.as_slice());
+            let time_stamp = prices.and_then(|prices| prices.last()).map_or(0, |price| price.ts());
+            for (source, state) in states.iter_mut() {
+                for signal_result in state.signals_pending.drain(..) {
+                    events.push(SessionEvent::new("ignore", time_stamp, &signal_result));
+                }
+                let prices = match prices {
+                    Some(prices) => prices,
+                    None => continue,
+                };
+                let context = self.context.replay_context(source, symbol)?;
+                let results_before = state.signals_result.len();
+                let account = Self::account(&mut self.accounts, source)?;
+                BacktestingSignals::backtest_finish(&context, state, account, &prices)?;
+                Self::collect_events(state, results_before, time_stamp, &mut self.tickets_opened, &mut events);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Evaluates the closed positions of every source so far, like `Backtester.backtest_signals`.
+    fn results(&self) -> PyResult<HashMap<String, BacktestResult>> {
+        let mut results_by_source: HashMap<String, Vec<SignalResult>> = HashMap::new();
+        for states in self.states.values() {
+            for (source, state) in states.iter() {
+                results_by_source.entry(source.clone()).or_default().extend(state.signals_result.iter().cloned());
+            }
+        }
+        let mut results = HashMap::new();
+        for (source, signal_results) in results_by_source {
+            let settings = self.context.strategies.get(&source)
+                .ok_or_else(|| BacktestError::Internal(format!("Strategy of source '{}' not set", source)))?.1.clone();
+            results.insert(source, BacktestingSignals::backtest_eval_results(&self.context.conditions, settings, signal_results, self.context.conditions.account.is_some(), self.context.conditions.initial_balance())?);
+        }
+        Ok(results)
+    }
+
+    fn signals_running(&self) -> Vec<SignalResult> {
+        self.states.values()
+            .flat_map(|states| states.values())
+            .flat_map(|state| state.signals_running.iter().cloned())
+            .collect()
+    }
+}
+
+impl BacktestSession {
+    fn check_running(&self) -> Result<(), BacktestError> {
+        if self.finished {
+            return Err(BacktestError::Argument("Backtest session is already finished".to_string()));
+        }
+        Ok(())
+    }
+
+    fn account<'a>(accounts: &'a mut HashMap<String, Account>, source: &str) -> Result<&'a mut Account, BacktestError> {
+        accounts.get_mut(source).ok_or_else(|| BacktestError::Internal(format!("Account of source '{}' not set", source)))
+    }
+
+    fn price_push(&mut self, symbol: String, kind: PriceKind, price: impl Price) -> Result<Vec<SessionEvent>, BacktestError> {
+        self.check_running()?;
+        let series = self.context.prices.entry(symbol.clone()).or_insert_with(|| PriceSeries::new(kind));
+        if series.kind() != kind {
+            return Err(BacktestError::PriceData(format!("Price kind does not match the prices already pushed for symbol '{}'", symbol)));
+        }
+        let prices = series.as_slice();
+        let time_stamp_last = prices.last().map(|last| last.ts());
+        if time_stamp_last.is_some_and(|time_stamp| time_stamp >= price.ts()) {
+            return Err(BacktestError::PriceData(format!("Prices for symbol '{}' have to be pushed in time order", symbol)));
+        }
+        // The previous price is evaluated with this one, so it stays even if it is older than the history
+        if let (Some(history_ms), Some(time_stamp_last)) = (self.history_ms, time_stamp_last) {
+            series.trim(Some(price.ts().saturating_sub(history_ms).min(time_stamp_last)), None);
+        }
+        series.push(&price);
+
+        let mut events = Vec::new();
+        let prices = self.context.prices[&symbol].as_slice();
+        let states = match self.states.get_mut(&symbol) {
+            Some(states) if prices.len() >= 2 => states,
+            _ => return Ok(events),
+        };
+        let p = prices.len() - 2;
+        let mut sources_stopped_out = Vec::new();
+        for (source, state) in states.iter_mut() {
+            let context = self.context.replay_context(source, &symbol)?;
+            let results_before = state.signals_result.len();
+            let account = Self::account(&mut self.accounts, source)?;
+            BacktestingSignals::backtest_step(&context, state, account, &prices, p, false)?;
+            Self::collect_events(state, results_before, prices.ts(p), &mut self.tickets_opened, &mut events);
+            if account.is_stopped_out() {
+                sources_stopped_out.push(source.clone());
+            }
+        }
+        for source in sources_stopped_out {
+            self.stop_out(&source, &mut events)?;
+        }
+        Ok(events)
+    }
+
+    /// Closes the open positions of `source` on every symbol at the last price evaluated for it.
+    fn stop_out(&mut self, source: &str, events: &mut Vec<SessionEvent>) -> Result<(), BacktestError> {
+        let account = Self::account(&mut self.accounts, source)?;
+        for (symbol, states) in self.states.iter_mut() {
+            let (state, prices) = match (states.get_mut(source), self.context.prices.get(symbol).map(|series| series.as_slice())) {
+                (Some(state), Some(prices)) if prices.len() >= 2 => (state, prices),
+                _ => continue,
+            };
+            let context = self.context.replay_context(source, symbol)?;
+            let p = prices.len() - 2;
+            let results_before = state.signals_result.len();
+            BacktestingSignals::stop_out(&context, state, account, &prices, p)?;
+            Self::collect_events(state, results_before, prices.ts(p), &mut self.tickets_opened, events);
+        }
+        Ok(())
+    }
+
+    /// Reports positions opened or partially closed since the last call and signals that were closed or ignored after `results_before`.
+    fn collect_events(state: &ReplayState, results_before: usize, time_stamp: u64, tickets_opened: &mut HashMap<u32, usize>, events: &mut Vec<SessionEvent>) {
+        for signal_result in state.signals_result[results_before..].iter() {
+            if !signal_result.position.opened {
+                events.push(SessionEvent::new("ignore", time_stamp, signal_result));
+                continue;
+            }
+            // The last fill is the one closing the position
+            let fills_partial = signal_result.position.fills.len().saturating_sub(1);
+            Self::collect_position_events(signal_result, fills_partial, time_stamp, tickets_opened, events);
+            tickets_opened.remove(&signal_result.ticket.unwrap_or_default());
+            events.push(SessionEvent::new("close", signal_result.position.time_stamp_close.unwrap_or(time_stamp), signal_result));
+        }
+        for signal_result in state.signals_running.iter() {
+            if signal_result.position.opened {
+                Self::collect_position_events(signal_result, signal_result.position.fills.len(), time_stamp, tickets_opened, events);
+            }
+        }
+    }
+
+    /// Reports the open of a position and its partial fills up to `fills_partial` that were not reported yet.
+    fn collect_position_events(signal_result: &SignalResult, fills_partial: usize, time_stamp: u64, tickets_opened: &mut HashMap<u32, usize>, events: &mut Vec<SessionEvent>) {
+        let fills_reported = tickets_opened.entry(signal_result.ticket.unwrap_or_default()).or_insert_with(|| {
+            events.push(SessionEvent::new("open", signal_result.position.time_stamp_open.unwrap_or(time_stamp), signal_result));
+            0
+        });
+        for fill in signal_result.position.fills[*fills_reported..fills_partial].iter() {
+            events.push(SessionEvent::new("partial", fill.time_stamp, signal_result));
+        }
+        *fills_reported = fills_partial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures::{self, MINUTE, SOURCE, SYMBOL, TUESDAY};
+    use crate::strategies::scale_out::ScaleOutRules;
+
+    fn session_with(strategy_rules: StrategyRules, history_minutes: Option<f32>) -> BacktestSession {
+        BacktestSession::new(fixtures::conditions(), fixtures::rules_of_source(strategy_rules), history_minutes).unwrap()
+    }
+
+    fn session(history_minutes: Option<f32>) -> BacktestSession {
+        session_with(fixtures::strategy_rules(), history_minutes)
+    }
+
+    /// Pushes a flat bar at `minute` and returns the kinds of the events it caused.
+    fn push(session: &mut BacktestSession, minute: u64, close: f32) -> Vec<String> {
+        let events = session.price_ohlc_push(SYMBOL.to_string(), PriceOhlc::new(TUESDAY + minute * MINUTE, close, close, close, close)).unwrap();
+        events.into_iter().map(|event| event.kind).collect()
+    }
+
+    #[test]
+    fn events_follow_the_pushed_prices() {
+        let mut session = session(None);
+        assert_eq!(session.signal_push(fixtures::signal("buy", 1.0, 1.2, 0)).unwrap(), 0);
+        assert!(push(&mut session, 0, 1.1).is_empty());
+        assert_eq!(push(&mut session, 1, 1.1), vec!["open"]);
+        assert_eq!(session.signals_running().len(), 1);
+        assert!(push(&mut session, 2, 1.2).is_empty());
+        assert_eq!(push(&mut session, 3, 1.2), vec!["close"]);
+        assert!(session.finish().unwrap().is_empty());
+
+        let results = session.results().unwrap();
+        assert_eq!(results[SOURCE].num_trades, 1);
+        assert!((results[SOURCE].profit - 0.1).abs() < 1e-5);
+        assert!(session.signal_push(fixtures::signal("buy", 1.0, 1.2, 5)).is_err());
+    }
+
+    #[test]
+    fn partial_closes_are_reported() {
+        let mut session = session_with(fixtures::strategy_rules().with_scale_out(ScaleOutRules::new(&[0.5], None)), None);
+        let mut signal = fixtures::signal("buy", 1.0, 1.2, 0);
+        signal.take_profit.push(1.3);
+        session.signal_push(signal).unwrap();
+        let events: Vec<Vec<String>> = [1.1, 1.1, 1.2, 1.3, 1.3].iter().enumerate()
+            .map(|(minute, &close)| push(&mut session, minute as u64, close))
+            .collect();
+        assert_eq!(events.concat(), vec!["open", "partial", "close"]);
+        assert!(events[3] == vec!["partial"] && events[4] == vec!["close"]);
+    }
+
+    #[test]
+    fn history_limits_the_prices_kept() {
+        let mut session = session(Some(2.0));
+        session.signal_push(fixtures::signal("buy", 1.0, 1.2, 0)).unwrap();
+        for minute in 0..10 {
+            push(&mut session, minute, 1.1);
+        }
+        assert_eq!(session.context.prices[SYMBOL].as_slice().time_stamps(), &[TUESDAY + 7 * MINUTE, TUESDAY + 8 * MINUTE, TUESDAY + 9 * MINUTE]);
+
+        // The previous price stays to be evaluated with the next one however old it is
+        assert!(push(&mut session, 20, 1.2).is_empty());
+        assert_eq!(session.context.prices[SYMBOL].as_slice().time_stamps(), &[TUESDAY + 9 * MINUTE, TUESDAY + 20 * MINUTE]);
+        assert_eq!(push(&mut session, 21, 1.2), vec!["close"]);
+        assert!((session.results().unwrap()[SOURCE].profit - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rejects_invalid_history_and_prices_out_of_order() {
+        let rules = fixtures::rules_of_source(fixtures::strategy_rules());
+        assert!(BacktestSession::new(fixtures::conditions(), rules, Some(0.0)).is_err());
+        let mut session = session(None);
+        push(&mut session, 1, 1.1);
+        let price = PriceOhlc::new(TUESDAY, 1.1, 1.1, 1.1, 1.1);
+        assert!(session.price_ohlc_push(SYMBOL.to_string(), price).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+// pyo3 0.20 expands #[pymethods] into impls inside anonymous consts, which newer toolchains flag.
+#![allow(non_local_definitions)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use pyo3::exceptions::PyUserWarning;
+use pyo3::prelude::*;
+
+use crate::algorithms::Algorithms;
+use crate::error::BacktestError;
+use crate::evaluation::backtest_conditions::BacktestConditions;
+use crate::evaluation::backtest_result::{BacktestResult, PortfolioResult};
+use crate::evaluation::backtesting_signals::BacktestingSignals;
+use crate::evaluation::signal::{Signal, SignalResult};
+use crate::evaluation::signal_update::SignalUpdate;
+use crate::prices::price::{Ohlc, PriceType, Tick};
+use crate::prices::price_arrays::PriceArrays;
+use crate::prices::price_csv::{CsvPrices, PriceCsv};
+use crate::prices::price_info::PriceInfo;
+use crate::prices::price_ingestion::{IngestionPolicy, IngestionReport};
+use crate::prices::price_manager::PriceManager;
+use crate::prices::price_ohlc::PriceOhlc;
+use crate::prices::price_resampler::PriceResampler;
+use crate::prices::price_series::PriceKind;
+use crate::prices::price_slice::PriceSlice;
+use crate::prices::price_snapshot::PriceSnapshot;
+use crate::prices::price_tick::PriceTick;
+use crate::strategies::strategy::StrategyRules;
+
+const MAX_THREADS: usize = 15;
+
+fn convert_price_type(type_tick: &str, type_ohlc: &str) -> Result<PriceType, BacktestError> {
+    let tick = match type_tick {
+        "ask" => Tick::Ask,
+        "bid" => Tick::Bid,
+        _ => return Err(BacktestError::Argument(format!("Invalid tick type '{}', expected 'ask' or 'bid'", type_tick))),
+    };
+    let ohlc = match type_ohlc {
+        "open" => Ohlc::Open,
+        "high" => Ohlc::High,
+        "low" => Ohlc::Low,
+        "close" => Ohlc::Close,
+        _ => return Err(BacktestError::Argument(format!("Invalid ohlc type '{}', expected 'open', 'high', 'low' or 'close'", type_ohlc))),
+    };
+    Ok((tick, ohlc))
+}
+
+/// An isolated backtesting universe with its own prices and thread pool.
+/// Independent instances can be used side by side and backtest concurrently from different Python threads.
+#[pyclass]
+pub struct Backtester {
+    price_manager: Arc<RwLock<PriceManager>>,
+    backtesting_signals: BacktestingSignals,
+}
+
+#[pymethods]
+impl Backtester {
+    #[new]
+    #[pyo3(signature = (max_threads=MAX_THREADS))]
+    fn new(max_threads: usize) -> PyResult<Self> {
+        let price_manager = Arc::new(RwLock::new(PriceManager::new()));
+        let backtesting_signals = BacktestingSignals::new(price_manager.clone(), max_threads)?;
+        Ok(Backtester {
+            price_manager,
+            backtesting_signals,
+        })
+    }
+
+    #[pyo3(signature = (prices, policy="keep_last"))]
+    fn prices_tick_add(&self, prices: HashMap<String, Vec<PriceTick>>, policy: &str) -> PyResult<HashMap<String, IngestionReport>> {
+        let policy = IngestionPolicy::parse(policy).map_err(BacktestError::Argument)?;
+        let mut price_manager = self.write_prices()?;
+        Ok(price_manager.add_prices(PriceKind::Tick, prices, policy).map_err(BacktestError::PriceData)?)
+    }
+
+    #[pyo3(signature = (prices, policy="keep_last"))]
+    fn prices_ohlc_add(&self, prices: HashMap<String, Vec<PriceOhlc>>, policy: &str) -> PyResult<HashMap<String, IngestionReport>> {
+        let policy = IngestionPolicy::parse(policy).map_err(BacktestError::Argument)?;
+        prices.values().flatten().try_for_each(PriceOhlc::validate).map_err(BacktestError::PriceData)?;
+        let mut price_manager = self.write_prices()?;
+        Ok(price_manager.add_prices(PriceKind::Ohlc, prices, policy).map_err(BacktestError::PriceData)?)
+    }
+
+    #[pyo3(signature = (symbol, time_stamps, bid, ask, sentiment=None, policy="keep_last"))]
+    #[allow(clippy::too_many_arguments)]
+    fn prices_add_arrays(&self, py: Python, symbol: String, time_stamps: &PyAny, bid: &PyAny, ask: &PyAny, sentiment: Option<&PyAny>, policy: &str) -> PyResult<IngestionReport> {
+        let policy = IngestionPolicy::parse(policy).map_err(BacktestError::Argument)?;
+        let series = PriceArrays::tick(py, time_stamps, bid, ask, sentiment).map_err(BacktestError::PriceData)?;
+        let mut price_manager = self.write_prices()?;
+        Ok(price_manager.add_series(symbol, series, policy).map_err(BacktestError::PriceData)?)
+    }
+
+    #[pyo3(signature = (symbol, time_stamps, open, high, low, close, ask_open=None, ask_high=None, ask_low=None, ask_close=None, policy="keep_last"))]
+    #[allow(clippy::too_many_arguments)]
+    fn prices_ohlc_add_arrays(&self, py: Python, symbol: String, time_stamps: &PyAny, open: &PyAny, high: &PyAny, low: &PyAny, close: &PyAny,
+            ask_open: Option<&PyAny>, ask_high: Option<&PyAny>, ask_low: Option<&PyAny>, ask_close: Option<&PyAny>, policy: &str) -> PyResult<IngestionReport> {
+        let policy = IngestionPolicy::parse(policy).map_err(BacktestError::Argument)?;
+        let ask = match (ask_open, ask_high, ask_low, ask_close) {
+            (Some(open), Some(high), Some(low), Some(close)) => Some([open, high, low, close]),
+            (None, None, None, None) => None,
+            _ => return Err(BacktestError::Argument("Ask arrays have to be given for open, high, low and close".to_string()).into()),
+        };
+        let series = PriceArrays::ohlc(py, time_stamps, [open, high, low, close], ask).map_err(BacktestError::PriceData)?;
+        let mut price_manager = self.write_prices()?;
+        Ok(price_manager.add_series(symbol, series, policy).map_err(BacktestError::PriceData)?)
+    }
+
+    #[pyo3(signature = (symbol, table, policy="keep_last"))]
+    fn prices_add_table(&self, py: Python, symbol: String, table: &PyAny, policy: &str) -> PyResult<IngestionReport> {
+        let policy = IngestionPolicy::parse(policy).map_err(BacktestError::Argument)?;
+        let series = PriceArrays::table(py, table).map_err(BacktestError::PriceData)?;
+        let mut price_manager = self.write_prices()?;
+        Ok(price_manager.add_series(symbol, series, policy).map_err(BacktestError::PriceData)?)
+    }
+
+    #[pyo3(signature = (path, symbols=None))]
+    fn prices_save(&self, py: Python, path: &str, symbols: Option<Vec<String>>) -> PyResult<usize> {
+        Ok(py.allow_threads(|| {
+            let price_manager = self.read_prices()?;
+            PriceSnapshot::save(path, &price_manager, symbols.as_deref()).map_err(BacktestError::Io)
+        })?)
+    }
+
+    #[pyo3(signature = (path, symbols=None))]
+    fn prices_load(&self, py: Python, path: &str, symbols: Option<Vec<String>>) -> PyResult<usize> {
+        let prices = py.allow_threads(|| PriceSnapshot::load(path, symbols.as_deref())).map_err(BacktestError::Io)?;
+        let num_symbols = prices.len();
+        let mut price_manager = self.write_prices()?;
+        price_manager.replace_prices(prices);
+        Ok(num_symbols)
+    }
+
+    #[pyo3(signature = (path, symbol, utc_offset_minutes=0, policy="keep_last"))]
+    fn prices_load_csv(&self, py: Python, path: &str, symbol: String, utc_offset_minutes: i32, policy: &str) -> PyResult<IngestionReport> {
+        let policy = IngestionPolicy::parse(policy).map_err(BacktestError::Argument)?;
+        let prices = py.allow_threads(|| PriceCsv::load(path, utc_offset_minutes)).map_err(BacktestError::Io)?;
+        let mut price_manager = self.write_prices()?;
+        let mut reports = match prices {
+            CsvPrices::Tick(prices) => price_manager.add_prices(PriceKind::Tick, HashMap::from([(symbol.clone(), prices)]), policy),
+            CsvPrices::Ohlc(prices) => price_manager.add_prices(PriceKind::Ohlc, HashMap::from([(symbol.clone(), prices)]), policy),
+        }.map_err(BacktestError::PriceData)?;
+        Ok(reports.remove(&symbol).expect("Report for loaded symbol"))
+    }
+
+    fn prices_resample(&self, symbol: &str, timeframe: &str, target_symbol: String) -> PyResult<usize> {
+        let timeframe_ms = PriceResampler::parse_timeframe(timeframe).map_err(BacktestError::Argument)?;
+        if target_symbol == symbol {
+            return Err(BacktestError::Argument(format!("Resampled prices of '{}' have to be stored under another symbol", symbol)).into());
+        }
+        let mut price_manager = self.write_prices()?;
+        let prices = price_manager.prices.get(symbol).ok_or_else(|| BacktestError::missing_price(symbol))?;
+        let resampled = PriceResampler::resample(&prices.as_slice(), timeframe_ms);
+        let num_prices = resampled.as_slice().len();
+        price_manager.replace_prices(HashMap::from([(target_symbol, resampled)]));
+        Ok(num_prices)
+    }
+
+    fn prices_list(&self) -> PyResult<Vec<PriceInfo>> {
+        let price_manager = self.read_prices()?;
+        Ok(price_manager.list())
+    }
+
+    fn prices_remove(&self, symbol: &str) -> PyResult<bool> {
+        let mut price_manager = self.write_prices()?;
+        Ok(price_manager.remove(symbol))
+    }
+
+    #[pyo3(signature = (symbol, time_stamp_from=None, time_stamp_to=None))]
+    fn prices_trim(&self, symbol: &str, time_stamp_from: Option<u64>, time_stamp_to: Option<u64>) -> PyResult<usize> {
+        let mut price_manager = self.write_prices()?;
+        Ok(price_manager.trim(symbol, time_stamp_from, time_stamp_to).map_err(BacktestError::MissingPrice)?)
+    }
+
+    fn prices_clear(&self) -> PyResult<()> {
+        let mut price_manager = self.write_prices()?;
+        price_manager.clear();
+        Ok(())
+    }
+
+    #[pyo3(signature = (conditions, strategy_rules, signals, updates=None))]
+    fn backtest_signals(&self, py: Python, conditions: BacktestConditions, strategy_rules: HashMap<String, StrategyRules>, signals: Vec<Signal>, updates: Option<Vec<SignalUpdate>>) -> PyResult<HashMap<String, BacktestResult>> {
+        self.warn_without_prices(py)?;
+        Ok(py.allow_threads(|| self.backtesting_signals.backtest_execute(conditions, &strategy_rules, signals, updates.unwrap_or_default()))?)
+    }
+
+    #[pyo3(signature = (conditions, strategy_rules, signals, allocations=None, updates=None))]
+    fn backtest_portfolio(&self, py: Python, conditions: BacktestConditions, strategy_rules: HashMap<String, StrategyRules>, signals: Vec<Signal>, allocations: Option<HashMap<String, f32>>, updates: Option<Vec<SignalUpdate>>) -> PyResult<PortfolioResult> {
+        self.warn_without_prices(py)?;
+        Ok(py.allow_threads(|| self.backtesting_signals.backtest_portfolio_execute(conditions, &strategy_rules, signals, allocations, updates.unwrap_or_default()))?)
+    }
+
+    fn signal_check_filter(&self, strategy_rules: StrategyRules, signal: Signal) -> PyResult<SignalResult> {
+        Ok(self.backtesting_signals.check_filter_extern(strategy_rules, signal)?)
+    }
+
+    fn signal_check_entry(&self, strategy_rules: StrategyRules, signal_result: SignalResult) -> PyResult<SignalResult> {
+        Ok(self.backtesting_signals.check_entry_extern(strategy_rules, signal_result)?)
+    }
+
+    fn signal_check_exit(&self, strategy_rules: StrategyRules, signal_result: SignalResult) -> PyResult<SignalResult> {
+        Ok(self.backtesting_signals.check_exit_extern(strategy_rules, signal_result)?)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn algo_mean(&self, symbol: &str, type_tick: &str, type_ohlc: &str, index_from: usize, index_to: usize, minutes: f32) -> PyResult<f32> {
+        let price_type = convert_price_type(type_tick, type_ohlc)?;
+        let price_manager = self.read_prices()?;
+        let prices = Self::prices_range(&price_manager, symbol, index_from, index_to)?;
+        Ok(Algorithms::calculate_mean(&price_type, &prices, minutes))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn algo_std_dev(&self, symbol: &str, type_tick: &str, type_ohlc: &str, index_from: usize, index_to: usize, minutes: f32, mean: f32) -> PyResult<f32> {
+        let price_type = convert_price_type(type_tick, type_ohlc)?;
+        let price_manager = self.read_prices()?;
+        let prices = Self::prices_range(&price_manager, symbol, index_from, index_to)?;
+        Ok(Algorithms::calculate_standard_deviation(&price_type, &prices, minutes, mean))
+    }
+}
+
+impl Backtester {
+    fn read_prices(&self) -> Result<RwLockReadGuard<'_, PriceManager>, BacktestError> {
+        self.price_manager.read().map_err(|_| BacktestError::Internal("Price storage is poisoned".to_string()))
+    }
+
+    fn write_prices(&self) -> Result<RwLockWriteGuard<'_, PriceManager>, BacktestError> {
+        self.price_manager.write().map_err(|_| BacktestError::Internal("Price storage is poisoned".to_string()))
+    }
+
+    /// Backtests without prices return empty results, which is reported as a Python warning rather than an error.
+    fn warn_without_prices(&self, py: Python) -> PyResult<()> {
+        if self.read_prices()?.prices.is_empty() {
+            PyErr::warn(py, py.get_type::<PyUserWarning>(), "Prices not set before backtesting. This will result in empty backtest results.", 1)?;
+        }
+        Ok(())
+    }
+
+    fn prices_range<'a>(price_manager: &'a PriceManager, symbol: &str, index_from: usize, index_to: usize) -> Result<PriceSlice<'a>, BacktestError> {
+        let prices = price_manager.prices.get(symbol).ok_or_else(|| BacktestError::missing_price(symbol))?.as_slice();
+        if index_from > index_to || index_to >= prices.len() {
+            return Err(BacktestError::Argument(format!("Index range {}..={} is out of bounds for {} prices of symbol '{}'", index_from, index_to, prices.len(), symbol)));
+        }
+        Ok(prices.slice(index_from..index_to+1))
+    }
+}
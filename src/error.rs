@@ -0,0 +1,91 @@
+use std::fmt;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+
+create_exception!(py_backtest_lab, BacktestLabError, PyValueError, "Base class of all errors raised by the backtest lab.");
+create_exception!(py_backtest_lab, StrategyConfigError, BacktestLabError, "Strategy rules are invalid or missing.");
+create_exception!(py_backtest_lab, StrategyStateError, BacktestLabError, "A signal result lacks state a strategy relies on.");
+create_exception!(py_backtest_lab, MissingPriceError, BacktestLabError, "No prices are stored for a symbol.");
+create_exception!(py_backtest_lab, ConditionsError, BacktestLabError, "Backtest conditions are invalid or incomplete.");
+create_exception!(py_backtest_lab, SignalError, BacktestLabError, "A signal is invalid.");
+create_exception!(py_backtest_lab, PriceDataError, BacktestLabError, "Prices passed in are invalid.");
+
+#[derive(Clone, Debug)]
+pub enum BacktestError {
+    StrategyConfig(String),
+    StrategyState(String),
+    MissingPrice(String),
+    Conditions(String),
+    Signal(String),
+    PriceData(String),
+    Argument(String),
+    Io(String),
+    Internal(String),
+}
+
+impl BacktestError {
+    pub fn missing_price(symbol: &str) -> Self {
+        BacktestError::MissingPrice(format!("No prices stored for symbol '{}'", symbol))
+    }
+
+    /// A value a position gets once it opens or closes is missing, e.g. in a signal result passed in from Python.
+    pub fn missing_state(name: &str) -> Self {
+        BacktestError::StrategyState(format!("{} not set", name))
+    }
+
+    pub fn missing_attribute(key: &str) -> Self {
+        BacktestError::StrategyState(format!("Strategy attribute '{}' not set", key))
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            BacktestError::StrategyConfig(message)
+            | BacktestError::StrategyState(message)
+            | BacktestError::MissingPrice(message)
+            | BacktestError::Conditions(message)
+            | BacktestError::Signal(message)
+            | BacktestError::PriceData(message)
+            | BacktestError::Argument(message)
+            | BacktestError::Io(message)
+            | BacktestError::Internal(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for BacktestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for BacktestError {}
+
+impl From<BacktestError> for PyErr {
+    fn from(error: BacktestError) -> PyErr {
+        let message = error.to_string();
+        match error {
+            BacktestError::StrategyConfig(_) => StrategyConfigError::new_err(message),
+            BacktestError::StrategyState(_) => StrategyStateError::new_err(message),
+            BacktestError::MissingPrice(_) => MissingPriceError::new_err(message),
+            BacktestError::Conditions(_) => ConditionsError::new_err(message),
+            BacktestError::Signal(_) => SignalError::new_err(message),
+            BacktestError::PriceData(_) => PriceDataError::new_err(message),
+            BacktestError::Argument(_) => PyValueError::new_err(message),
+            BacktestError::Io(_) => PyIOError::new_err(message),
+            BacktestError::Internal(_) => BacktestLabError::new_err(message),
+        }
+    }
+}
+
+pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("BacktestLabError", py.get_type::<BacktestLabError>())?;
+    m.add("StrategyConfigError", py.get_type::<StrategyConfigError>())?;
+    m.add("StrategyStateError", py.get_type::<StrategyStateError>())?;
+    m.add("MissingPriceError", py.get_type::<MissingPriceError>())?;
+    m.add("ConditionsError", py.get_type::<ConditionsError>())?;
+    m.add("SignalError", py.get_type::<SignalError>())?;
+    m.add("PriceDataError", py.get_type::<PriceDataError>())?;
+    Ok(())
+}
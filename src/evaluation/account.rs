@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use dict_derive::FromPyObject;
+
+use super::backtest_conditions::BacktestConditions;
+
+const MARGIN_CALL_LEVEL: f32 = 1.0;
+const STOP_OUT_LEVEL: f32 = 0.5;
+
+/// Account as given in the backtest conditions. Levels are ratios of equity to used margin, so `1.0` is a margin level of 100%.
+#[derive(Clone, Debug, FromPyObject)]
+pub struct AccountRules {
+    pub balance: f32,
+    pub compounding: Option<bool>,
+    pub margin_call_level: Option<f32>,
+    pub stop_out_level: Option<f32>,
+}
+
+impl AccountRules {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.balance.is_nan() || self.balance <= 0.0 {
+            return Err("Account balance has to be greater than 0");
+        }
+        let margin_call_level = self.margin_call_level.unwrap_or(MARGIN_CALL_LEVEL);
+        let stop_out_level = self.stop_out_level.unwrap_or(STOP_OUT_LEVEL);
+        if stop_out_level.is_nan() || stop_out_level < 0.0 || stop_out_level > margin_call_level {
+            return Err("Account stop out level has to be at least 0 and at most the margin call level");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Exposure {
+    margin: f32,
+    unrealized: f32,
+}
+
+/// Balance of one replay, realized profit is booked when a position closes. Without account rules the balance starts at
+/// the initial equity of the conditions and neither margin calls nor stop outs happen.
+#[derive(Clone, Debug)]
+pub struct Account {
+    balance_initial: f32,
+    balance: f32,
+    compounding: bool,
+    margin_call_level: Option<f32>,
+    stop_out_level: Option<f32>,
+    max_margin: Option<f32>,
+    exposures: HashMap<(String, String), Exposure>,
+}
+
+impl Account {
+    pub fn new(conditions: &BacktestConditions) -> Self {
+        let balance = conditions.initial_balance();
+        let rules = conditions.account.as_ref();
+        Account {
+            balance_initial: balance,
+            balance,
+            compounding: rules.and_then(|rules| rules.compounding).unwrap_or(true),
+            margin_call_level: rules.map(|rules| rules.margin_call_level.unwrap_or(MARGIN_CALL_LEVEL)),
+            stop_out_level: rules.map(|rules| rules.stop_out_level.unwrap_or(STOP_OUT_LEVEL)),
+            max_margin: None,
+            exposures: HashMap::new(),
+        }
+    }
+
+    /// Account shared by several sources. Without account rules the maximum margin of the conditions caps the margin
+    /// of all positions open at the same time, as the sources are not evaluated one by one.
+    pub fn shared(conditions: &BacktestConditions) -> Self {
+        Account {
+            max_margin: conditions.account.is_none().then_some(conditions.max_margin),
+            ..Account::new(conditions)
+        }
+    }
+
+    /// Balance plus the profit open positions would make if they were closed at their last price.
+    pub fn equity(&self) -> f32 {
+        self.balance + self.exposures.values().map(|exposure| exposure.unrealized).sum::<f32>()
+    }
+
+    pub fn margin_used(&self) -> f32 {
+        self.exposures.values().map(|exposure| exposure.margin).sum()
+    }
+
+    /// Equity sizers work with, the starting balance if profits are not compounded.
+    pub fn sizing_equity(&self) -> f32 {
+        if self.compounding { self.equity() } else { self.balance_initial }
+    }
+
+    /// A new position needs the margin level to stay above the margin call level after opening it.
+    pub fn can_open(&self, margin: f32) -> bool {
+        match (self.margin_call_level, self.max_margin) {
+            (Some(level), _) => self.equity() >= level * (self.margin_used() + margin),
+            (None, Some(max_margin)) => self.margin_used() + margin < max_margin,
+            (None, None) => true,
+        }
+    }
+
+    pub fn is_stopped_out(&self) -> bool {
+        let margin_used = self.margin_used();
+        self.stop_out_level.is_some_and(|level| margin_used > 0.0 && self.equity() < level * margin_used)
+    }
+
+    /// Holds the margin of a position opened by `source` on `symbol` until their exposure is updated.
+    pub fn reserve(&mut self, source: &str, symbol: &str, margin: f32) {
+        self.exposures.entry((source.to_string(), symbol.to_string())).or_default().margin += margin;
+    }
+
+    pub fn update_exposure(&mut self, source: &str, symbol: &str, margin: f32, unrealized: f32) {
+        self.exposures.insert((source.to_string(), symbol.to_string()), Exposure { margin, unrealized });
+    }
+
+    pub fn realize(&mut self, delta: f32) {
+        self.balance += delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    fn rules(balance: f32) -> AccountRules {
+        AccountRules { balance, compounding: None, margin_call_level: None, stop_out_level: None }
+    }
+
+    fn account(rules: AccountRules) -> Account {
+        Account::new(&BacktestConditions { account: Some(rules), ..fixtures::conditions() })
+    }
+
+    #[test]
+    fn margin_call_level_limits_new_positions() {
+        let mut account = account(rules(1000.0));
+        account.reserve("A", "EURUSD", 600.0);
+        assert!(account.can_open(400.0));
+        assert!(!account.can_open(500.0));
+
+        account.update_exposure("A", "EURUSD", 600.0, 200.0);
+        assert_eq!(account.equity(), 1200.0);
+        assert!(account.can_open(500.0));
+        assert!(!account.is_stopped_out());
+    }
+
+    #[test]
+    fn stops_out_below_the_stop_out_level() {
+        let mut account = account(rules(1000.0));
+        account.update_exposure("A", "EURUSD", 600.0, -690.0);
+        assert!(!account.is_stopped_out());
+        account.update_exposure("A", "EURUSD", 600.0, -710.0);
+        assert!(account.is_stopped_out());
+        account.update_exposure("A", "EURUSD", 0.0, 0.0);
+        assert!(!account.is_stopped_out());
+    }
+
+    #[test]
+    fn sizing_equity_compounds_unless_disabled() {
+        let mut compounding = account(rules(1000.0));
+        let mut fixed = account(AccountRules { compounding: Some(false), ..rules(1000.0) });
+        for account in [&mut compounding, &mut fixed] {
+            account.realize(100.0);
+            account.update_exposure("A", "EURUSD", 10.0, 50.0);
+        }
+        assert_eq!(compounding.sizing_equity(), 1150.0);
+        assert_eq!(fixed.sizing_equity(), 1000.0);
+    }
+
+    #[test]
+    fn without_rules_only_a_shared_account_caps_the_margin() {
+        let conditions = fixtures::conditions();
+        let mut account = Account::new(&conditions);
+        account.reserve("A", "EURUSD", 2.0 * conditions.max_margin);
+        account.update_exposure("A", "EURUSD", 2.0 * conditions.max_margin, -2.0 * conditions.max_margin);
+        assert!(account.can_open(conditions.max_margin));
+        assert!(!account.is_stopped_out());
+
+        let mut shared = Account::shared(&conditions);
+        assert!(shared.can_open(conditions.max_margin - 1.0));
+        shared.reserve("A", "EURUSD", 1.0);
+        assert!(!shared.can_open(conditions.max_margin - 1.0));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(rules(1000.0).validate().is_ok());
+        assert!(rules(0.0).validate().is_err());
+        assert!(rules(f32::NAN).validate().is_err());
+        assert!(AccountRules { stop_out_level: Some(1.5), ..rules(1000.0) }.validate().is_err());
+        assert!(AccountRules { margin_call_level: Some(2.0), stop_out_level: Some(1.5), ..rules(1000.0) }.validate().is_ok());
+    }
+}
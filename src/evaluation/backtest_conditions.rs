@@ -2,10 +2,111 @@ use std::collections::HashMap;
 
 use dict_derive::FromPyObject;
 
+use crate::error::BacktestError;
+use crate::prices::spread::Spread;
+use super::account::AccountRules;
+use super::intrabar::{Intrabar, IntrabarRules};
+use super::slippage::{Slippage, SlippageRules};
+use super::swap::{Swap, SwapRules};
+use super::trading_calendar::{CalendarRules, TradingCalendar};
+
 #[derive(Clone, Debug, FromPyObject)]
 pub struct BacktestConditions {
     pub max_margin: f32,
     pub commission: f32,
     pub lot_size: f32,
+    pub initial_equity: Option<f32>,
+    pub account: Option<AccountRules>,
     pub contract_sizes: HashMap<String, u32>,
-}
\ No newline at end of file
+    pub leverage: Option<f32>,
+    pub margin_rates: Option<HashMap<String, f32>>,
+    pub spreads: Option<HashMap<String, Spread>>,
+    pub slippage: Option<SlippageRules>,
+    pub swaps: Option<SwapRules>,
+    pub intrabar: Option<IntrabarRules>,
+    pub calendar: Option<CalendarRules>,
+    pub max_price_delay_seconds: Option<u64>,
+    pub entry_timeout_minutes: Option<f32>,
+    pub max_threads: Option<usize>,
+}
+
+impl BacktestConditions {
+    /// Balance the account starts with, the initial equity without account rules and the maximum margin if neither is given.
+    pub fn initial_balance(&self) -> f32 {
+        match &self.account {
+            Some(account) => account.balance,
+            None => self.initial_equity.unwrap_or(self.max_margin),
+        }
+    }
+
+    /// Fraction of the value of a position held as margin, the rate of the symbol or one over the leverage.
+    pub fn margin_rate(&self, symbol: &str) -> f32 {
+        match self.margin_rates.as_ref().and_then(|margin_rates| margin_rates.get(symbol)) {
+            Some(&margin_rate) => margin_rate,
+            None => 1.0 / self.leverage.unwrap_or(1.0),
+        }
+    }
+
+    pub fn margin(&self, symbol: &str, price: f32, lots: f32) -> Result<f32, BacktestError> {
+        Ok(price * self.contract_size(symbol)? * lots * self.margin_rate(symbol))
+    }
+
+    pub fn validate_margin(&self) -> Result<(), &'static str> {
+        if self.leverage.is_some_and(|leverage| leverage.is_nan() || leverage <= 0.0) {
+            return Err("Leverage has to be greater than 0");
+        }
+        if self.margin_rates.iter().flat_map(|margin_rates| margin_rates.values()).any(|margin_rate| margin_rate.is_nan() || *margin_rate <= 0.0) {
+            return Err("Margin rates have to be greater than 0");
+        }
+        Ok(())
+    }
+
+    pub fn contract_size(&self, symbol: &str) -> Result<f32, BacktestError> {
+        self.contract_sizes.get(symbol)
+            .map(|&contract_size| contract_size as f32)
+            .ok_or_else(|| BacktestError::Conditions(format!("Missing contract size for symbol '{}'", symbol)))
+    }
+}
+
+/// Rules built from the backtest conditions once before a backtest starts.
+pub struct ConditionRules {
+    pub calendar: TradingCalendar,
+    pub slippage: Slippage,
+    pub swap: Swap,
+    pub intrabar: Intrabar,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn margin_rates_override_the_leverage() {
+        let conditions = BacktestConditions {
+            contract_sizes: HashMap::from([("EURUSD".to_string(), 100_000), ("GBPUSD".to_string(), 100_000)]),
+            leverage: Some(50.0),
+            margin_rates: Some(HashMap::from([("GBPUSD".to_string(), 0.05)])),
+            ..fixtures::conditions()
+        };
+        assert_eq!(conditions.margin_rate("EURUSD"), 0.02);
+        assert_eq!(conditions.margin_rate("GBPUSD"), 0.05);
+        assert!((conditions.margin("EURUSD", 1.1, 0.5).unwrap() - 1100.0).abs() < 1e-2);
+        assert!((conditions.margin("GBPUSD", 1.3, 0.5).unwrap() - 3250.0).abs() < 1e-2);
+        assert!(matches!(conditions.margin("USDJPY", 150.0, 0.5), Err(BacktestError::Conditions(_))));
+        assert_eq!(fixtures::conditions().margin_rate("EURUSD"), 1.0);
+    }
+
+    #[test]
+    fn rejects_invalid_margin() {
+        assert!(fixtures::conditions().validate_margin().is_ok());
+        let invalid = [
+            BacktestConditions { leverage: Some(0.0), ..fixtures::conditions() },
+            BacktestConditions { leverage: Some(f32::NAN), ..fixtures::conditions() },
+            BacktestConditions { margin_rates: Some(HashMap::from([("EURUSD".to_string(), -0.1)])), ..fixtures::conditions() },
+        ];
+        for conditions in invalid.iter() {
+            assert!(conditions.validate_margin().is_err());
+        }
+    }
+}
@@ -1,36 +1,63 @@
+use std::collections::HashMap;
+
 use dict_derive::IntoPyObject;
 
+use super::engine_settings::EngineSettings;
 use super::position::Position;
 
 #[derive(Debug, IntoPyObject)]
 pub struct IgnoredCounts {
     pub missing_margin: u32,
+    pub invalid_size: u32,
     pub price_gap: u32,
     pub no_entry: u32,
     pub end_of_day: u32,
 }
 
+/// Highest margin of the positions open at the same time, `utilization` relative to the starting balance.
+#[derive(Debug, IntoPyObject)]
+pub struct MarginUsage {
+    pub max: f32,
+    pub utilization: f32,
+}
+
 #[derive(Debug, IntoPyObject)]
 pub struct BacktestResult {
-    profit: f32,
-    num_trades: usize,
-    sortino_ratio: f32,
-    positions: Vec<Position>,
-    ignored_counts: IgnoredCounts,
-    hit_rate: f32,
-    profit_per_day: f32,
+    pub profit: f32,
+    pub balance: f32,
+    pub num_trades: usize,
+    pub ambiguous_trades: usize,
+    pub sortino_ratio: f32,
+    pub positions: Vec<Position>,
+    pub ignored_counts: IgnoredCounts,
+    pub margin: MarginUsage,
+    pub hit_rate: f32,
+    pub profit_per_day: f32,
+    pub settings: EngineSettings,
 }
 
 impl BacktestResult {
-    pub fn new(profit: f32, num_trades: usize, sortino_ratio: f32, positions: Vec<Position>, ignored_counts: IgnoredCounts, hit_rate: f32, profit_per_day: f32) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(profit: f32, balance: f32, num_trades: usize, ambiguous_trades: usize, sortino_ratio: f32, positions: Vec<Position>, ignored_counts: IgnoredCounts, margin: MarginUsage, hit_rate: f32, profit_per_day: f32, settings: EngineSettings) -> Self {
         BacktestResult {
             profit,
+            balance,
             num_trades,
+            ambiguous_trades,
             sortino_ratio,
             positions,
             ignored_counts,
+            margin,
             hit_rate,
             profit_per_day,
+            settings,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Result of sources sharing one account, `sources` attributes the positions of the portfolio to the source that took them.
+#[derive(Debug, IntoPyObject)]
+pub struct PortfolioResult {
+    pub portfolio: BacktestResult,
+    pub sources: HashMap<String, BacktestResult>,
+}
@@ -1,34 +1,74 @@
-use std::sync::RwLock;
+use std::cmp::Reverse;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 use std::vec::Vec;
-use std::collections::HashMap;
+use std::collections::{BinaryHeap, HashMap};
 
-use chrono::Datelike;
 use rayon::prelude::*;
 
 use crate::algorithms::Algorithms;
+use crate::error::BacktestError;
 use crate::prices::price::{Ohlc, Price, Tick};
+use crate::prices::price_series::{OhlcColumns, PriceSeries};
+use crate::prices::price_slice::PriceSlice;
 use crate::prices::price_manager::PriceManager;
+use crate::strategies::sizer::sizer::SizingContext;
 use crate::strategies::strategy::{Strategy, StrategyManager, StrategyRules};
-use super::backtest_conditions::BacktestConditions;
-use super::backtest_result::{BacktestResult, IgnoredCounts};
-use super::position::Position;
+use super::account::Account;
+use super::backtest_conditions::{BacktestConditions, ConditionRules};
+use super::backtest_result::{BacktestResult, IgnoredCounts, MarginUsage, PortfolioResult};
+use super::engine_settings::EngineSettings;
+use super::position::{PositionFill, SIZE_EPSILON};
+use super::replay_state::{ReplayContext, ReplayState};
+use super::intrabar::{Intrabar, IntrabarStep};
+use super::order::{Order, OrderType, TimeInForce};
 use super::signal::{Signal, SignalResult};
+use super::signal_update::{SignalUpdate, SignalUpdateKind};
+use super::slippage::Slippage;
+use super::swap::Swap;
+use super::trading_calendar::{EndOfSession, TradingCalendar};
 
-const MAX_PRICE_DELAY_SECONDS: i128 = 60*15;
-const MAX_THREADS: usize = 15;
+/// Source with its strategy, signals and their updates as resolved before a backtest starts.
+struct SourcePrepared {
+    name: String,
+    strategy: Strategy,
+    signals: Vec<Signal>,
+    updates: Vec<SignalUpdate>,
+}
+
+/// Source replayed with the share `allocation` of the account equity to size its positions with.
+struct ReplaySource<'a> {
+    name: &'a str,
+    strategy: &'a Strategy,
+    allocation: f32,
+    signals: Vec<Signal>,
+    updates: Vec<SignalUpdate>,
+}
 
-pub struct BacktestingSignals<'a> {
-    price_manager: &'a RwLock<PriceManager>,
+pub struct BacktestingSignals {
+    price_manager: Arc<RwLock<PriceManager>>,
+    pool: rayon::ThreadPool,
 }
 
-impl<'a> BacktestingSignals<'a> {
-    pub fn new(price_manager: &'a RwLock<PriceManager>) -> BacktestingSignals {
-        BacktestingSignals {
+impl BacktestingSignals {
+    pub fn new(price_manager: Arc<RwLock<PriceManager>>, max_threads: usize) -> Result<BacktestingSignals, BacktestError> {
+        Ok(BacktestingSignals {
             price_manager,
-        }
+            pool: Self::build_pool(max_threads)?,
+        })
+    }
+
+    fn build_pool(max_threads: usize) -> Result<rayon::ThreadPool, BacktestError> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(max_threads)
+            .build()
+            .map_err(|err| BacktestError::Internal(format!("Failed to create thread pool: {}", err)))
+    }
+
+    fn read_prices(&self) -> Result<RwLockReadGuard<'_, PriceManager>, BacktestError> {
+        self.price_manager.read().map_err(|_| BacktestError::Internal("Price storage is poisoned".to_string()))
     }
 
-    fn signal_check_init(&self, strategy: &Strategy, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) {
+    fn signal_check_init(strategy: &Strategy, signal_result: &mut SignalResult, prices: &PriceSlice) {
         if strategy.filter.check_filter(&signal_result.signal, prices) {
             return;
         }
@@ -36,174 +76,422 @@ impl<'a> BacktestingSignals<'a> {
         strategy.entry.on_init(signal_result, prices);
     }
 
-    fn signal_check_open(&self, strategy: &Strategy, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) {
-        let price_open = strategy.entry.check_entry(signal_result, prices);
-        if price_open.is_none() {
-            return;
+    /// Places, amends or cancels the entry order and opens the position once the order fills on the last price.
+    /// Without a replay `account`, e.g. for checks from Python, the position is neither sized nor checked for margin.
+    fn signal_check_open(strategy: &Strategy, slippage: &Slippage, intrabar: &IntrabarStep, signal_result: &mut SignalResult, prices: &PriceSlice, account: Option<(&ReplayContext, &mut Account)>) -> Result<(), BacktestError> {
+        let last_price = prices.last().ok_or_else(|| BacktestError::missing_price(&signal_result.signal.symbol))?;
+        let time_stamp = last_price.ts();
+        match strategy.entry.order(signal_result, prices)? {
+            Some(request) => match &signal_result.order {
+                Some(order) if order.is_request(&request) => {}
+                Some(_) => {
+                    signal_result.order = Some(Order::new(&request, time_stamp));
+                    // Orders following a moving price are amended on every price, one event holds the last amendment of a row
+                    if signal_result.order_events.last().is_some_and(|event| event.kind == "amended") {
+                        signal_result.order_events.pop();
+                    }
+                    signal_result.record_order_event("amended", time_stamp);
+                }
+                None => {
+                    signal_result.order = Some(Order::new(&request, time_stamp));
+                    signal_result.record_order_event("placed", time_stamp);
+                }
+            },
+            None => {
+                signal_result.record_order_event("cancelled", time_stamp);
+                signal_result.order = None;
+            }
         }
-        let last_price = prices.last().expect("No prices found");
-        signal_result.position.time_stamp_open = Some(last_price.ts());
-        signal_result.position.price_open = price_open;
+
+        let order = match signal_result.order.as_mut() {
+            Some(order) => order,
+            None => return Ok(()),
+        };
+        let triggered = order.triggered;
+        let price_fill = order.fill(&signal_result.signal.action, &last_price)?;
+        let order_type = order.kind()?;
+        if order.triggered && !triggered {
+            signal_result.record_order_event("triggered", time_stamp);
+        }
+        let price_open = match price_fill {
+            Some(price_fill) => slippage.apply(order_type, signal_result, prices, price_fill, true),
+            None => {
+                if strategy.time_in_force == TimeInForce::ImmediateOrCancel {
+                    Self::expire_order(signal_result, time_stamp);
+                }
+                return Ok(());
+            }
+        };
+        signal_result.record_order_event("filled", time_stamp);
+        signal_result.position.price_open = Some(price_open);
+        if let Some((context, account)) = account {
+            let sizing = SizingContext {
+                equity: account.sizing_equity() * context.allocation,
+                lot_size: context.conditions.lot_size,
+                contract_size: context.contract_size,
+            };
+            let lots = strategy.sizer.size(signal_result, prices, &sizing)?;
+            if !(lots > 0.0 && lots.is_finite()) {
+                signal_result.record_order_event("rejected", time_stamp);
+                signal_result.position.price_open = None;
+                signal_result.position.ignored.invalid_size = true;
+                return Ok(());
+            }
+            let margin = context.conditions.margin(context.symbol, price_open, lots)?;
+            if !account.can_open(margin) {
+                signal_result.record_order_event("rejected", time_stamp);
+                signal_result.position.price_open = None;
+                signal_result.position.ignored.missing_margin = true;
+                return Ok(());
+            }
+            account.reserve(context.source, context.symbol, margin);
+            signal_result.position.lots = Some(lots);
+            signal_result.position.margin = Some(margin);
+        }
+        signal_result.position.time_stamp_open = Some(time_stamp);
         strategy.exit.on_open(signal_result, prices);
 
-        self.signal_check_close(strategy, signal_result, prices);
+        Self::signal_check_close(strategy, slippage, intrabar, signal_result, prices)?;
         if signal_result.position.closed {
-            return;
+            return Ok(());
         }
 
         signal_result.position.opened = true;
+        Ok(())
     }
 
-    fn signal_check_close(&self, strategy: &Strategy, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) {
-        let last_price = prices.last().expect("No prices found");
+    fn signal_check_close(strategy: &Strategy, slippage: &Slippage, intrabar: &IntrabarStep, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<(), BacktestError> {
+        let last_price = prices.last().ok_or_else(|| BacktestError::missing_price(&signal_result.signal.symbol))?;
         let price_close = {
-            if Algorithms::check_stop_loss_hit(&signal_result.signal, last_price.as_ref()) {
-                Some(signal_result.signal.stop_loss)
-            } else {
-                let price = strategy.exit.check_exit(signal_result, prices);
-                if price.is_some() {
+            if Algorithms::check_stop_loss_hit(signal_result, &last_price) {
+                // The exit of a copy without slippage tells whether the price also reached a level to close at
+                let mut signal_result_exit = signal_result.clone();
+                let fills_before = signal_result_exit.position.fills.len();
+                let price_exit = Self::signal_check_exit(strategy, &Slippage::default(), &mut signal_result_exit, prices)?;
+                let target = signal_result_exit.position.fills.get(fills_before).map(|fill| fill.price).or(price_exit);
+                let stop_loss_first = match target {
+                    Some(target) => {
+                        signal_result.position.intrabar_ambiguous = true;
+                        intrabar.stop_loss_first(signal_result, &last_price, target)
+                    }
+                    None => true,
+                };
+                if stop_loss_first {
+                    Some(Self::price_close_stop_loss(slippage, signal_result, prices, &last_price))
+                } else {
+                    // What partial exits leave of the position is closed at the stop loss afterwards
+                    match Self::signal_check_exit(strategy, slippage, signal_result, prices)? {
+                        Some(price_close) => Some(price_close),
+                        None => Some(Self::price_close_stop_loss(slippage, signal_result, prices, &last_price)),
+                    }
                 }
-                price
+            } else {
+                Self::signal_check_exit(strategy, slippage, signal_result, prices)?
             }
         };
 
         if price_close.is_none() {
-            return;
+            return Self::signal_update_stop(strategy, signal_result, prices);
         }
         
         signal_result.position.time_stamp_close = Some(last_price.ts());
         signal_result.position.price_close = price_close;
         
         signal_result.position.closed = true;
+        Ok(())
     }
 
-    fn is_weekend(timestamp: u64) -> bool {
-        let datetime = chrono::DateTime::from_timestamp(timestamp as i64, 0).unwrap();
-        let weekday = datetime.weekday();
-        
-        match weekday {
-            chrono::Weekday::Sat | chrono::Weekday::Sun => true,
-            _ => false,
-        }    
-    }
-
-    fn backtest(&self, conditions: &BacktestConditions, strategy: &Strategy, mut signals: Vec<Signal>, symbol: String) -> Vec<SignalResult> {
-        let mut signals_result: Vec<SignalResult> = Vec::new();
-        let mut signals_running: Vec<SignalResult> = Vec::new();
-        let price_manager = self.price_manager.read().unwrap();
-        let prices: Option<&Vec<Box<dyn Price>>> = price_manager.prices.get(&symbol);
-        if prices.is_none() || prices.unwrap().len() == 0 {
-            return signals_result;
-        }
-        let prices = prices.unwrap();
-        for p in 0..prices.len() - 1 {
-            // Check if signals have to be initiated
-            while !signals.is_empty() && prices[p+1].ts() > signals[0].time_stamp {
-                let signal = signals.remove(0);
-                let mut signal_result = SignalResult::new(signal.clone());
-                let delay = (prices[p+1].ts() as i128 - signal.time_stamp as i128) / 1000;
-                if delay > MAX_PRICE_DELAY_SECONDS || !Self::is_weekend(signal.time_stamp) {
-                    continue;
-                }
-                self.signal_check_init(strategy, &mut signal_result, &prices[..p+1]);
-                if !signal_result.position.inited {
-                    continue;
-                }
-                signals_running.push(signal_result);
+    /// Close price of the exit strategy after the partial exits of the scale out.
+    fn signal_check_exit(strategy: &Strategy, slippage: &Slippage, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
+        let price_close_scaled = match &strategy.scale_out {
+            Some(scale_out) => scale_out.check_partial_exits(strategy.exit.as_ref(), slippage, signal_result, prices)?,
+            None => None,
+        };
+        match price_close_scaled {
+            Some(price_close) => Ok(Some(price_close)),
+            None => Ok(strategy.exit.check_exit(signal_result, prices)?
+                .map(|price_close| slippage.apply(strategy.exit.order_type(), signal_result, prices, price_close, false))),
+        }
+    }
+
+    fn price_close_stop_loss(slippage: &Slippage, signal_result: &SignalResult, prices: &PriceSlice, price: &impl Price) -> f32 {
+        let price_stop_loss = Self::price_stop_loss(signal_result, price);
+        slippage.apply(OrderType::Stop, signal_result, prices, price_stop_loss, false)
+    }
+
+    /// Moves the stop loss to the one of the stop manager if it is tighter, it is checked from the next price on.
+    fn signal_update_stop(strategy: &Strategy, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<(), BacktestError> {
+        let stop_manager = match &strategy.stop_manager {
+            Some(stop_manager) => stop_manager,
+            None => return Ok(()),
+        };
+        let stop_loss = match stop_manager.update(signal_result, prices)? {
+            Some(stop_loss) => stop_loss,
+            None => return Ok(()),
+        };
+        let time_stamp = prices.last().ok_or_else(|| BacktestError::missing_price(&signal_result.signal.symbol))?.ts();
+        signal_result.tighten_stop(time_stamp, stop_loss, stop_manager.name());
+        Ok(())
+    }
+
+    /// The stop loss, or the open of `price` if it gapped through a stop loss the position already had before.
+    fn price_stop_loss(signal_result: &SignalResult, price: &impl Price) -> f32 {
+        let stop_loss = signal_result.stop_loss();
+        if signal_result.position.time_stamp_open.is_none_or(|time_stamp| time_stamp >= price.ts()) {
+            return stop_loss;
+        }
+        if signal_result.signal.action == "buy" {
+            price.get(&(Tick::Bid, Ohlc::Open)).min(stop_loss)
+        } else {
+            price.get(&(Tick::Ask, Ohlc::Open)).max(stop_loss)
+        }
+    }
+
+    /// Ends the signal without entry because its order ran out of time.
+    fn expire_order(signal_result: &mut SignalResult, time_stamp: u64) {
+        signal_result.record_order_event("expired", time_stamp);
+        signal_result.position.ignored.no_entry = true;
+    }
+
+    /// Processes the price at index `p`, `prices` has to contain the following price as well.
+    pub fn backtest_step(context: &ReplayContext, state: &mut ReplayState, account: &mut Account, prices: &PriceSlice, p: usize, end_of_prices: bool) -> Result<(), BacktestError> {
+        Self::replay_step(context, state, account, prices, p, end_of_prices)?;
+        Self::update_exposure(context, state, account, prices, p)
+    }
+
+    fn replay_step(context: &ReplayContext, state: &mut ReplayState, account: &mut Account, prices: &PriceSlice, p: usize, end_of_prices: bool) -> Result<(), BacktestError> {
+        let ReplayContext { calendar, slippage, swap, strategy, settings, symbol, .. } = *context;
+
+        // Charge swaps of positions held over a rollover since the previous price
+        if p > 0 {
+            for signal_result in state.signals_running.iter_mut().filter(|result| result.position.opened) {
+                let lots = signal_result.position.lots.ok_or_else(|| BacktestError::missing_state("Lots"))? * signal_result.position.size_open();
+                signal_result.position.swap += swap.charge(calendar, symbol, &signal_result.signal.action, lots, prices.ts(p-1), prices.ts(p));
             }
+        }
+        let ReplayState { signals_pending: signals, signals_running, signals_result, .. } = state;
 
-            if signals_running.len() == 0 {
+        // Check if signals have to be initiated
+        while !signals.is_empty() && prices.ts(p+1) > signals[0].signal.time_stamp {
+            let mut signal_result = signals.remove(0);
+            let time_stamp = signal_result.signal.time_stamp;
+            let delay = (prices.ts(p+1) as i128 - time_stamp as i128) / 1000;
+            if delay > settings.max_price_delay_seconds as i128 || calendar.session(symbol, time_stamp).is_none() {
                 continue;
             }
-
-            // Check if there is a time gap in the prices
-            if (prices[p+1].ts() as i128 - prices[p].ts() as i128) / 1000 > MAX_PRICE_DELAY_SECONDS as i128 {
-                signals_running.iter_mut().for_each(|result| result.position.ignored.price_gap = true);
-                signals_result.extend(signals_running.clone());
-                signals_running.clear();
+            Self::signal_check_init(strategy, &mut signal_result, &prices.slice(..p+1));
+            if !signal_result.position.inited {
                 continue;
             }
-            
-            // Close signals if this is the last price for this day (22:59)
-            if p == prices.len() - 2 || prices[p].ts() / (24*60*60*1000) != (prices[p+1].ts()) / (24*60*60*1000) {
-                for signal_result in signals_running.iter_mut() {
-                    if signal_result.position.opened {
-                        continue;
-                    }
-                    signal_result.position.ignored.end_of_day = true;
-                    signals_result.push(signal_result.clone());
-                }
-                signals_running.retain(|result: &SignalResult| !result.position.ignored.end_of_day);
-                signals_running.iter_mut().for_each(|result| result.position.closed = true);
+            signals_running.push(signal_result);
+        }
+
+        if signals_running.is_empty() {
+            return Ok(());
+        }
+
+        let end_of_session = calendar.session(symbol, prices.ts(p)) != calendar.session(symbol, prices.ts(p+1));
+        let intrabar = IntrabarStep::new(context.intrabar.policy, context.intrabar_prices.as_ref(), prices.ts(p), prices.ts(p+1));
+
+        // Check if there is a time gap in the prices, gaps between sessions are expected
+        if !end_of_session && (prices.ts(p+1) as i128 - prices.ts(p) as i128) / 1000 > settings.max_price_delay_seconds as i128 {
+            signals_running.iter_mut().for_each(|result| result.position.ignored.price_gap = true);
+            signals_result.append(signals_running);
+            return Ok(());
+        }
+        
+        // Close signals if this is the last price of the session, orders only good for the day expire with it
+        if end_of_prices || (end_of_session && calendar.end_of_session == EndOfSession::Close) {
+            Self::close_running(state, prices.ts(p));
+        } else if end_of_session && strategy.time_in_force == TimeInForce::Day {
+            let ReplayState { signals_running, signals_result, .. } = state;
+            for signal_result in signals_running.iter_mut().filter(|result| !result.position.opened) {
+                Self::expire_order(signal_result, prices.ts(p));
+                signals_result.push(signal_result.clone());
             }
+            signals_running.retain(|result| !result.position.ignored.no_entry);
+        }
+        let ReplayState { signals_running, signals_result, .. } = state;
 
-            
-            // Check if not opened signals hit stop loss or are too old and remove them
-            for signal_result in signals_running.iter_mut() {
-                if signal_result.position.opened || (!Algorithms::check_stop_loss_hit(&signal_result.signal, prices[p].as_ref()) && 
-                    prices[p].ts() as i128 - signal_result.signal.time_stamp as i128 <= 20 * 60 * 1000) {
-                    continue;
-                }
+        // Check if not opened signals hit stop loss or are too old and remove them
+        for signal_result in signals_running.iter_mut() {
+            if signal_result.position.opened || (!Algorithms::check_stop_loss_hit(signal_result, &prices.at(p)) && 
+                prices.ts(p) as i128 - signal_result.signal.time_stamp as i128 <= settings.entry_timeout_ms() as i128) {
+                continue;
+            }
+            if Algorithms::check_stop_loss_hit(signal_result, &prices.at(p)) {
+                signal_result.record_order_event("cancelled", prices.ts(p));
                 signal_result.position.ignored.no_entry = true;
-                signals_result.push(signal_result.clone());
+            } else {
+                Self::expire_order(signal_result, prices.ts(p));
             }
-            signals_running.retain(|result: &SignalResult| !result.position.ignored.no_entry);
+            signals_result.push(signal_result.clone());
+        }
+        signals_running.retain(|result: &SignalResult| !result.position.ignored.no_entry);
 
-            // Check if open signals have to be opened
-            for signal_result in signals_running.iter_mut() {
-                if signal_result.position.opened {
-                    continue;
-                }
-                self.signal_check_open(strategy, signal_result, &prices[..p+1]);
+        // Check if open signals have to be opened, signals without margin for their position or whose order expired are removed
+        for signal_result in signals_running.iter_mut() {
+            if signal_result.position.opened {
+                continue;
             }
+            Self::signal_check_open(strategy, slippage, &intrabar, signal_result, &prices.slice(..p+1), Some((context, &mut *account)))?;
+        }
+        let not_entered = |result: &SignalResult| {
+            let ignored = &result.position.ignored;
+            ignored.missing_margin || ignored.invalid_size || ignored.no_entry
+        };
+        signals_result.extend(signals_running.iter().filter(|result| not_entered(result)).cloned());
+        signals_running.retain(|result| !not_entered(result));
 
-            // Check for exit conditions
-            for signal_result in signals_running.iter_mut() {
-                if !signal_result.position.opened {
-                    continue;
-                }
-                self.signal_check_close(strategy, signal_result, &prices[..p+1]);
+        // Check for exit conditions
+        for signal_result in signals_running.iter_mut() {
+            if !signal_result.position.opened {
+                continue;
             }
+            Self::signal_check_close(strategy, slippage, &intrabar, signal_result, &prices.slice(..p+1))?;
+        }
 
-            // Execute exit for closed signals
-            for signal_result in signals_running.iter_mut() {
-                if !signal_result.position.closed || !signal_result.position.opened {
-                    continue;
-                }
-                if signal_result.position.price_close == None {
-                    let price_type = if signal_result.signal.action == "buy" { (Tick::Bid, Ohlc::Close) } else { (Tick::Ask, Ohlc::Close) };
-                    signal_result.position.price_close = Some(prices[p].get(&price_type));
-                    signal_result.position.time_stamp_close = Some(prices[p].ts());
-                }
-                let mut delta = signal_result.position.price_close.expect("Price close not set") - signal_result.position.price_open.expect("Price open not set");
-                let action_multiplier = if signal_result.signal.action == "buy" { 1.0 } else { -1.0 };
-                let contract_size = *conditions.contract_sizes.get(&symbol).expect("Contract size not found") as f32;
-                delta *= contract_size * conditions.lot_size * action_multiplier;
-                delta -= conditions.commission * conditions.lot_size * 2.0;
-                signal_result.position.delta = Some(delta);
-                signals_result.push(signal_result.clone());
+        // Updates up to the following price apply after the exits, positions they close are closed at this price
+        Self::apply_updates(state, prices.ts(p), prices.ts(p+1))?;
+        Self::execute_exits(context, state, account, prices, p)
+    }
+
+    /// Applies the updates before `time_stamp_next` to the running signals, updates of signals that are not running are dropped.
+    fn apply_updates(state: &mut ReplayState, time_stamp: u64, time_stamp_next: u64) -> Result<(), BacktestError> {
+        let ReplayState { signals_running, signals_result, updates_pending, .. } = state;
+        let num_due = updates_pending.partition_point(|update| update.time_stamp < time_stamp_next);
+        for update in updates_pending.drain(..num_due) {
+            let signal_result = signals_running.iter_mut().find(|result| result.signal.id.as_ref() == Some(&update.signal_id));
+            if let Some(signal_result) = signal_result {
+                Self::signal_apply_update(signal_result, update, time_stamp)?;
             }
+        }
+        signals_result.extend(signals_running.iter().filter(|result| result.position.ignored.no_entry).cloned());
+        signals_running.retain(|result| !result.position.ignored.no_entry);
+        Ok(())
+    }
 
-            // Remove closed signals
-            signals_running.retain(|result| !result.position.closed);
+    fn signal_apply_update(signal_result: &mut SignalResult, update: SignalUpdate, time_stamp: u64) -> Result<(), BacktestError> {
+        let position = &mut signal_result.position;
+        let applied = match update.kind()? {
+            _ if position.closed => false,
+            SignalUpdateKind::MoveStopLoss(stop_loss) => {
+                position.move_stop(time_stamp, stop_loss, "signal_update");
+                true
+            }
+            SignalUpdateKind::Close if position.opened => {
+                position.closed = true;
+                true
+            }
+            SignalUpdateKind::Close | SignalUpdateKind::Cancel if !position.opened => {
+                position.ignored.no_entry = true;
+                signal_result.record_order_event("cancelled", time_stamp);
+                true
+            }
+            SignalUpdateKind::Close | SignalUpdateKind::Cancel => false,
+        };
+        signal_result.record_update(update, time_stamp, applied);
+        Ok(())
+    }
 
-            if signals_running.len() == 0 && signals.len() == 0 {
-                break;
+    /// Ends the replay at the last price, closing all open positions and ignoring signals still waiting for their entry.
+    pub fn backtest_finish(context: &ReplayContext, state: &mut ReplayState, account: &mut Account, prices: &PriceSlice) -> Result<(), BacktestError> {
+        if prices.is_empty() {
+            return Ok(());
+        }
+        Self::close_running(state, prices.ts(prices.len() - 1));
+        Self::execute_exits(context, state, account, prices, prices.len() - 1)?;
+        Self::update_exposure(context, state, account, prices, prices.len() - 1)
+    }
+
+    /// Closes all open positions at price `p` because the account fell below its stop out level.
+    pub fn stop_out(context: &ReplayContext, state: &mut ReplayState, account: &mut Account, prices: &PriceSlice, p: usize) -> Result<(), BacktestError> {
+        for signal_result in state.signals_running.iter_mut().filter(|result| result.position.opened) {
+            signal_result.position.closed = true;
+            signal_result.position.stopped_out = true;
+        }
+        Self::execute_exits(context, state, account, prices, p)?;
+        Self::update_exposure(context, state, account, prices, p)
+    }
+
+    /// Hands the margin and the profit of the open positions at price `p` to the account.
+    fn update_exposure(context: &ReplayContext, state: &ReplayState, account: &mut Account, prices: &PriceSlice, p: usize) -> Result<(), BacktestError> {
+        let mut margin = 0.0;
+        let mut unrealized = 0.0;
+        for signal_result in state.signals_running.iter().filter(|result| result.position.opened) {
+            let position = &signal_result.position;
+            let price_open = position.price_open.ok_or_else(|| BacktestError::missing_state("Price open"))?;
+            let price_type = if signal_result.signal.action == "buy" { (Tick::Bid, Ohlc::Close) } else { (Tick::Ask, Ohlc::Close) };
+            let gross = position.fills.iter().map(|fill| (fill.price - price_open) * fill.size).sum::<f32>()
+                + (prices.get(p, &price_type) - price_open) * position.size_open();
+            margin += position.margin.ok_or_else(|| BacktestError::missing_state("Margin"))? * position.size_open();
+            unrealized += Self::position_delta(context, signal_result, gross)?;
+        }
+        account.update_exposure(context.source, context.symbol, margin, unrealized);
+        Ok(())
+    }
+
+    /// Profit of a position from its `gross` price difference weighted by size, commission included.
+    fn position_delta(context: &ReplayContext, signal_result: &SignalResult, gross: f32) -> Result<f32, BacktestError> {
+        let lots = signal_result.position.lots.ok_or_else(|| BacktestError::missing_state("Lots"))?;
+        let action_multiplier = if signal_result.signal.action == "buy" { 1.0 } else { -1.0 };
+        let mut delta = gross;
+        delta *= context.contract_size * lots * action_multiplier;
+        delta -= context.conditions.commission * lots * 2.0;
+        Ok(delta + signal_result.position.swap)
+    }
+
+    fn close_running(state: &mut ReplayState, time_stamp: u64) {
+        for signal_result in state.signals_running.iter_mut() {
+            if signal_result.position.opened {
+                continue;
+            }
+            signal_result.record_order_event("expired", time_stamp);
+            signal_result.position.ignored.end_of_day = true;
+            state.signals_result.push(signal_result.clone());
+        }
+        state.signals_running.retain(|result: &SignalResult| !result.position.ignored.end_of_day);
+        state.signals_running.iter_mut().for_each(|result| result.position.closed = true);
+    }
+
+    fn execute_exits(context: &ReplayContext, state: &mut ReplayState, account: &mut Account, prices: &PriceSlice, p: usize) -> Result<(), BacktestError> {
+        for signal_result in state.signals_running.iter_mut() {
+            if !signal_result.position.closed || !signal_result.position.opened {
+                continue;
+            }
+            if signal_result.position.price_close.is_none() {
+                let price_type = if signal_result.signal.action == "buy" { (Tick::Bid, Ohlc::Close) } else { (Tick::Ask, Ohlc::Close) };
+                let price_close = prices.get(p, &price_type);
+                signal_result.position.price_close = Some(context.slippage.apply(OrderType::Market, signal_result, &prices.slice(..p+1), price_close, false));
+                signal_result.position.time_stamp_close = Some(prices.ts(p));
+            }
+            let position = &mut signal_result.position;
+            let size_open = position.size_open();
+            if size_open > SIZE_EPSILON {
+                let time_stamp_close = position.time_stamp_close.ok_or_else(|| BacktestError::missing_state("Time stamp close"))?;
+                let price_close = position.price_close.ok_or_else(|| BacktestError::missing_state("Price close"))?;
+                position.fills.push(PositionFill::new(time_stamp_close, price_close, size_open));
             }
+
+            // The profit adds up the fills, a position closed at once has a single fill of the whole size
+            let price_open = position.price_open.ok_or_else(|| BacktestError::missing_state("Price open"))?;
+            let gross = position.fills.iter().map(|fill| (fill.price - price_open) * fill.size).sum::<f32>();
+            let delta = Self::position_delta(context, signal_result, gross)?;
+            signal_result.position.delta = Some(delta);
+            account.realize(delta);
+            state.signals_result.push(signal_result.clone());
         }
-        signals_result
+
+        // Remove closed signals
+        state.signals_running.retain(|result| !result.position.closed);
+        Ok(())
     }
 
     fn group_signals_by_source(&self, signals: Vec<Signal>) -> HashMap<String, Vec<Signal>> {
         let mut signals_by_source: HashMap<String, Vec<Signal>> = HashMap::new();
         for signal in signals {
             let source = signal.source.clone();
-            if signals_by_source.contains_key(&source) {
-                let signals_for_source = signals_by_source.get_mut(&source).unwrap();
-                signals_for_source.push(signal);
-            } else {
-                signals_by_source.insert(source, vec![signal]);
-            }
+            signals_by_source.entry(source).or_default().push(signal);
         }
         signals_by_source
     }
@@ -212,26 +500,31 @@ impl<'a> BacktestingSignals<'a> {
         let mut signals_by_symbol: HashMap<String, Vec<Signal>> = HashMap::new();
         for signal in signals {
             let symbol = signal.symbol.clone();
-            if signals_by_symbol.contains_key(&symbol) {
-                let signals_for_symbol = signals_by_symbol.get_mut(&symbol).unwrap();
-                signals_for_symbol.push(signal);
-            } else {
-                signals_by_symbol.insert(symbol, vec![signal]);
-            }
+            signals_by_symbol.entry(symbol).or_default().push(signal);
         }
         signals_by_symbol
     }
 
-    fn backtest_eval_results(&self, conditions: BacktestConditions, mut results: Vec<SignalResult>) -> BacktestResult {
+    /// Evaluates the signal results of a replay. Unless the margin was `margin_checked` when positions opened, positions
+    /// beyond the maximum margin of the conditions are ignored. The balance and margin utilization are relative to
+    /// `balance_start`, the part of the starting balance the positions were taken with.
+    pub fn backtest_eval_results(conditions: &BacktestConditions, settings: EngineSettings, mut results: Vec<SignalResult>, margin_checked: bool, balance_start: f32) -> Result<BacktestResult, BacktestError> {
         results.sort_by_key(|result| result.position.time_stamp_open);
 
         let mut ignored_counts = IgnoredCounts {
             missing_margin: 0,
+            invalid_size: 0,
             price_gap: 0,
             no_entry: 0,
             end_of_day: 0,
         };
         for result in results.iter_mut() {
+            if result.position.ignored.missing_margin {
+                ignored_counts.missing_margin += 1;
+            }
+            if result.position.ignored.invalid_size {
+                ignored_counts.invalid_size += 1;
+            }
             if result.position.ignored.price_gap {
                 ignored_counts.price_gap += 1;	
             }
@@ -244,37 +537,37 @@ impl<'a> BacktestingSignals<'a> {
         }
         results.retain(|result| result.position.closed);
 
-        let mut rolling_window: Vec<Position> = Vec::new();
-        let mut rolling_sum = 0.0;
+        // Close time and margin of the positions taken that are still open
+        let mut margins_open: Vec<(u64, f32)> = Vec::new();
+        let mut margin_max: f32 = 0.0;
         let mut profit = 0.0;
         let mut num_trades = 0;
         let mut hit_rate = 0.0;
         let mut positions = Vec::new();
 
         let mut realized_returns_daily: Vec<f32> = Vec::new();
-        let mut realized_returns_window: Vec<Position> = Vec::new();
+        // Close time and profit of the positions of the current day
+        let mut realized_returns_window: Vec<(u64, f32)> = Vec::new();
         let mut negative_deltas: Vec<f32> = Vec::new();
         let risk_free_rate = 0.04 / (21.0 * 12.0) + 1.0;
 
         for result in results {
-            while !rolling_window.is_empty() && result.position.time_stamp_open > rolling_window[0].time_stamp_close {
-                let contract_size = *conditions.contract_sizes.get(&result.signal.symbol).expect("Contract size not found") as f32;
-                rolling_sum -= rolling_window.remove(0).price_close.expect("Price close not set") * contract_size * conditions.lot_size;
-            }
-
-            let contract_size = *conditions.contract_sizes.get(&result.signal.symbol).expect("Contract size not found") as f32;
-            let margin = result.position.price_open.expect("Price open not set") * contract_size * conditions.lot_size;
-            if rolling_sum + margin < conditions.max_margin {
-                rolling_window.push(result.position.clone());
-                rolling_sum += margin;
-                let delta = result.position.delta.expect("Delta not set");
+            let time_stamp_open = result.position.time_stamp_open.ok_or_else(|| BacktestError::missing_state("Time stamp open"))?;
+            let time_stamp_close = result.position.time_stamp_close.ok_or_else(|| BacktestError::missing_state("Time stamp close"))?;
+            margins_open.retain(|&(time_stamp_close, _)| time_stamp_close >= time_stamp_open);
+            let margin_used = margins_open.iter().map(|&(_, margin)| margin).sum::<f32>();
+            let margin = result.position.margin.ok_or_else(|| BacktestError::missing_state("Margin"))?;
+            if margin_checked || margin_used + margin < conditions.max_margin {
+                margins_open.push((time_stamp_close, margin));
+                margin_max = margin_max.max(margin_used + margin);
+                let delta = result.position.delta.ok_or_else(|| BacktestError::missing_state("Delta"))?;
                 profit += delta;
                 num_trades += 1;
                 hit_rate += if delta > 0.0 { 1.0 } else { -1.0 };
 
                 positions.push(result.position.clone());
-                if realized_returns_window.len() > 0 && result.position.time_stamp_close.unwrap() as i128 - realized_returns_window[0].time_stamp_close.unwrap() as i128 > 24*60*60*1000 {
-                    let realized_return = realized_returns_window.iter().map(|position| position.delta.expect("Delta not set")).sum::<f32>() / (margin * realized_returns_window.len() as f32) * 1.0;
+                if !realized_returns_window.is_empty() && time_stamp_close as i128 - realized_returns_window[0].0 as i128 > 24*60*60*1000 {
+                    let realized_return = realized_returns_window.iter().map(|&(_, delta)| delta).sum::<f32>() / (margin * realized_returns_window.len() as f32) * 1.0;
                     realized_returns_daily.push(realized_return);
                     realized_returns_window.clear();
                     
@@ -283,7 +576,7 @@ impl<'a> BacktestingSignals<'a> {
                         negative_deltas.push(target_return * target_return);
                     }
                 }
-                realized_returns_window.push(result.position.clone());
+                realized_returns_window.push((time_stamp_close, delta));
             }
             else {
                 ignored_counts.missing_margin += 1;
@@ -301,61 +594,835 @@ impl<'a> BacktestingSignals<'a> {
                 0.0
             }
         };
-        BacktestResult::new(profit, num_trades, sortino_ratio, positions, ignored_counts, hit_rate, profit_per_day)
+        let ambiguous_trades = positions.iter().filter(|position| position.intrabar_ambiguous).count();
+        let margin = MarginUsage {
+            max: margin_max,
+            utilization: if balance_start > 0.0 { margin_max / balance_start } else { 0.0 },
+        };
+        Ok(BacktestResult::new(profit, balance_start + profit, num_trades, ambiguous_trades, sortino_ratio, positions, ignored_counts, margin, hit_rate, profit_per_day, settings))
+    }
+
+    /// Replays the prices of all symbols of a source in time order, so that its positions share one account.
+    fn backtest_source(&self, conditions: &BacktestConditions, rules: &ConditionRules, source: ReplaySource, max_threads: usize) -> Result<(Vec<SignalResult>, EngineSettings), BacktestError> {
+        let mut results = self.backtest_replay(conditions, rules, vec![source], Account::new(conditions), max_threads)?;
+        results.pop().ok_or_else(|| BacktestError::Internal("Result of replayed source not set".to_string()))
+    }
+
+    /// Replays the prices of all symbols of all `sources` in time order, so that their positions share `account`.
+    /// Returns the signal results and engine settings of every source in the order of `sources`.
+    fn backtest_replay(&self, conditions: &BacktestConditions, rules: &ConditionRules, mut sources: Vec<ReplaySource>, mut account: Account, max_threads: usize) -> Result<Vec<(Vec<SignalResult>, EngineSettings)>, BacktestError> {
+        let settings: Vec<EngineSettings> = sources.iter().map(|source| EngineSettings::new(conditions, source.strategy, max_threads)).collect();
+        let price_manager = self.read_prices()?;
+
+        // Signals of symbols without prices are dropped, the sources of a symbol are replayed next to each other
+        let mut units: Vec<(usize, String, ReplayState)> = Vec::new();
+        for (s, source) in sources.iter_mut().enumerate() {
+            let symbols_by_id: HashMap<&str, &str> = source.signals.iter()
+                .filter_map(|signal| signal.id.as_deref().map(|id| (id, signal.symbol.as_str())))
+                .collect();
+            let mut updates_by_symbol: HashMap<String, Vec<SignalUpdate>> = HashMap::new();
+            for update in std::mem::take(&mut source.updates) {
+                let symbol = symbols_by_id[update.signal_id.as_str()].to_string();
+                updates_by_symbol.entry(symbol).or_default().push(update);
+            }
+            for (symbol, signals) in self.group_signals_by_symbol(std::mem::take(&mut source.signals)) {
+                if price_manager.prices.get(&symbol).is_some_and(|series| !series.is_empty()) {
+                    let updates = updates_by_symbol.remove(&symbol).unwrap_or_default();
+                    units.push((s, symbol, ReplayState::new(signals, updates)));
+                }
+            }
+        }
+        units.sort_by(|(source_a, symbol_a, _), (source_b, symbol_b, _)| symbol_a.cmp(symbol_b).then(source_a.cmp(source_b)));
+        let (units, mut states): (Vec<(usize, String)>, Vec<ReplayState>) = units.into_iter()
+            .map(|(s, symbol, state)| ((s, symbol), state))
+            .unzip();
+
+        // Prices are prepared once per symbol
+        let mut symbols: Vec<&str> = Vec::new();
+        let mut units_symbol: Vec<usize> = Vec::new();
+        for (_, symbol) in units.iter() {
+            if symbols.last() != Some(&symbol.as_str()) {
+                symbols.push(symbol);
+            }
+            units_symbol.push(symbols.len() - 1);
+        }
+        let series_all: Vec<&PriceSeries> = symbols.iter().map(|symbol| &price_manager.prices[*symbol]).collect();
+        // Symbols drilled down into are looked up like the replayed ones, their spreads apply as well
+        let symbols_intrabar: Vec<Option<&str>> = symbols.iter().map(|symbol| rules.intrabar.drill_down_symbol(symbol)).collect();
+        let series_intrabar: Vec<Option<&PriceSeries>> = symbols_intrabar.iter()
+            .map(|symbol| symbol.and_then(|symbol| price_manager.prices.get(symbol)))
+            .collect();
+        let spread_ask = |symbol: &str, series: &PriceSeries| conditions.spreads.as_ref()
+            .and_then(|spreads| spreads.get(symbol))
+            .and_then(|spread| series.spread_ask(spread));
+        let spreads_ask: Vec<Option<Arc<OhlcColumns>>> = symbols.iter().zip(series_all.iter())
+            .map(|(symbol, series)| spread_ask(symbol, series))
+            .collect();
+        let spreads_ask_intrabar: Vec<Option<Arc<OhlcColumns>>> = symbols_intrabar.iter().zip(series_intrabar.iter())
+            .map(|(symbol, series)| symbol.zip(*series).and_then(|(symbol, series)| spread_ask(symbol, series)))
+            .collect();
+        let prices_symbols: Vec<PriceSlice> = series_all.iter().zip(spreads_ask.iter())
+            .map(|(series, spread_ask)| Self::with_spread_ask(series, spread_ask))
+            .collect();
+        let prices_intrabar: Vec<Option<PriceSlice>> = series_intrabar.iter().zip(spreads_ask_intrabar.iter())
+            .map(|(series, spread_ask)| series.map(|series| Self::with_spread_ask(series, spread_ask)))
+            .collect();
+        let prices: Vec<PriceSlice> = units_symbol.iter().map(|&y| prices_symbols[y]).collect();
+        let contexts = units.iter().zip(units_symbol.iter())
+            .map(|((s, symbol), &y)| Ok(ReplayContext {
+                conditions,
+                calendar: &rules.calendar,
+                slippage: &rules.slippage,
+                swap: &rules.swap,
+                intrabar: &rules.intrabar,
+                intrabar_prices: prices_intrabar[y],
+                strategy: sources[*s].strategy,
+                settings: &settings[*s],
+                source: sources[*s].name,
+                symbol,
+                contract_size: conditions.contract_size(symbol)?,
+                allocation: sources[*s].allocation,
+            }))
+            .collect::<Result<Vec<ReplayContext>, BacktestError>>()?;
+
+        let mut replayed: Vec<Option<usize>> = vec![None; units.len()];
+        let mut queue: BinaryHeap<Reverse<(u64, usize, usize)>> = prices.iter().enumerate()
+            .filter(|(_, prices)| prices.len() >= 2)
+            .map(|(u, prices)| Reverse((prices.ts(0), u, 0)))
+            .collect();
+        while let Some(Reverse((_, u, p))) = queue.pop() {
+            let p_last = prices[u].len() - 2;
+            Self::backtest_step(&contexts[u], &mut states[u], &mut account, &prices[u], p, p == p_last)?;
+            replayed[u] = Some(p);
+            if account.is_stopped_out() {
+                for (o, p_other) in replayed.iter().enumerate() {
+                    if let Some(p_other) = *p_other {
+                        Self::stop_out(&contexts[o], &mut states[o], &mut account, &prices[o], p_other)?;
+                    }
+                }
+            }
+            if p < p_last && !states[u].is_finished() {
+                queue.push(Reverse((prices[u].ts(p + 1), u, p + 1)));
+            }
+        }
+
+        let mut results: Vec<Vec<SignalResult>> = vec![Vec::new(); sources.len()];
+        for ((s, _), state) in units.iter().zip(states) {
+            results[*s].extend(state.signals_result);
+        }
+        Ok(results.into_iter().zip(settings).collect())
     }
 
-    fn backtest_source(&self, source: &str, conditions: &BacktestConditions, strategy_rules: &HashMap<String, StrategyRules>, signals: Vec<Signal>) -> Vec<SignalResult> {
-        let stategy_rules_source = strategy_rules.get(source).expect("Strategy rules not found");
-        let strategy = StrategyManager::convert_rules_to_strategy(stategy_rules_source).expect("Failed to convert rules to strategy");
-        let mut results: Vec<SignalResult> = Vec::new();
-        let signals_by_symbol = self.group_signals_by_symbol(signals.clone());
-        for (symbol, signals) in signals_by_symbol.iter() {
-            let result = self.backtest(conditions, &strategy, signals.clone(), symbol.clone());
-            results.extend(result);
+    fn with_spread_ask<'a>(series: &'a PriceSeries, spread_ask: &'a Option<Arc<OhlcColumns>>) -> PriceSlice<'a> {
+        match spread_ask {
+            Some(ask) => series.as_slice().with_ask(ask.as_slice()),
+            None => series.as_slice(),
         }
-        results
     }
 
-    pub fn backtest_execute(&self, conditions: BacktestConditions, strategy_rules: &HashMap<String, StrategyRules>, signals: Vec<Signal>) -> HashMap<String, BacktestResult> {
-        if self.price_manager.read().unwrap().prices.is_empty() {
-            println!("WARNING: Prices not set before backtesting. This will result in empty backtest results.");
+    /// Checks the conditions and builds the rules they describe.
+    pub fn prepare_conditions(conditions: &BacktestConditions) -> Result<ConditionRules, BacktestError> {
+        EngineSettings::validate_entry_timeout(conditions.entry_timeout_minutes).map_err(|err| BacktestError::Conditions(err.to_string()))?;
+        conditions.validate_margin().map_err(|err| BacktestError::Conditions(err.to_string()))?;
+        if let Some(account) = &conditions.account {
+            if conditions.initial_equity.is_some() {
+                return Err(BacktestError::Conditions("Starting balance has to be given either as initial_equity or in account, not both".to_string()));
+            }
+            account.validate().map_err(|err| BacktestError::Conditions(err.to_string()))?;
         }
-        
-        let signals_by_source = self.group_signals_by_source(signals);
-        let pool = rayon::ThreadPoolBuilder::new().num_threads(MAX_THREADS).build().unwrap();
-
-        let result = pool.install(|| {
-            signals_by_source.par_iter().map(|(source, signals)| {
-                let signal_results = self.backtest_source(source, &conditions, &strategy_rules, signals.clone());
-                let backtest_result = self.backtest_eval_results(conditions.clone(), signal_results);
-                (source.clone(), backtest_result)
-            }).collect::<HashMap<String, BacktestResult>>()
-        });
-        result
+        for spread in conditions.spreads.iter().flat_map(|spreads| spreads.values()) {
+            spread.validate().map_err(|err| BacktestError::Conditions(err.to_string()))?;
+        }
+        Ok(ConditionRules {
+            calendar: TradingCalendar::new(conditions.calendar.as_ref()).map_err(BacktestError::Conditions)?,
+            slippage: Slippage::new(conditions.slippage.as_ref()).map_err(BacktestError::Conditions)?,
+            swap: Swap::new(conditions.swaps.as_ref()).map_err(BacktestError::Conditions)?,
+            intrabar: Intrabar::new(conditions.intrabar.as_ref()).map_err(BacktestError::Conditions)?,
+        })
+    }
+
+    /// Resolves the strategy of every source and checks signals, their updates and conditions before any backtest starts.
+    fn backtest_prepare(&self, conditions: &BacktestConditions, strategy_rules: &HashMap<String, StrategyRules>, signals: Vec<Signal>, updates: Vec<SignalUpdate>) -> Result<Vec<SourcePrepared>, BacktestError> {
+        let mut signals_by_id: HashMap<&str, &Signal> = HashMap::new();
+        for signal in signals.iter() {
+            signal.validate()?;
+            conditions.contract_size(&signal.symbol)?;
+            if let Some(id) = &signal.id {
+                if signals_by_id.insert(id, signal).is_some() {
+                    return Err(BacktestError::Signal(format!("Signal id '{}' is not unique", id)));
+                }
+            }
+        }
+        let mut updates_by_source: HashMap<String, Vec<SignalUpdate>> = HashMap::new();
+        for update in updates {
+            update.kind()?;
+            let signal = signals_by_id.get(update.signal_id.as_str())
+                .ok_or_else(|| BacktestError::Signal(format!("Update of unknown signal '{}'", update.signal_id)))?;
+            if update.time_stamp < signal.time_stamp {
+                return Err(BacktestError::Signal(format!("Update of signal '{}' is older than the signal", update.signal_id)));
+            }
+            updates_by_source.entry(signal.source.clone()).or_default().push(update);
+        }
+
+        let mut sources = Vec::new();
+        for (source, signals) in self.group_signals_by_source(signals) {
+            let rules = strategy_rules.get(&source)
+                .ok_or_else(|| BacktestError::StrategyConfig(format!("Missing strategy rules for source '{}'", source)))?;
+            let strategy = StrategyManager::convert_rules_to_strategy(rules)?;
+            let updates = updates_by_source.remove(&source).unwrap_or_default();
+            sources.push(SourcePrepared { name: source, strategy, signals, updates });
+        }
+        Ok(sources)
     }
 
-    pub fn check_filter_extern(&self, strategy_rules: StrategyRules, signal: Signal) -> SignalResult {
-        let strategy = StrategyManager::convert_rules_to_strategy(&strategy_rules).expect("Failed to convert rules to strategy");
-        let price_manager = self.price_manager.read().unwrap();
-        let prices = price_manager.prices.get(&signal.symbol).expect("Prices not found");
+    pub fn backtest_execute(&self, conditions: BacktestConditions, strategy_rules: &HashMap<String, StrategyRules>, signals: Vec<Signal>, updates: Vec<SignalUpdate>) -> Result<HashMap<String, BacktestResult>, BacktestError> {
+        let rules = Self::prepare_conditions(&conditions)?;
+        let sources = self.backtest_prepare(&conditions, strategy_rules, signals, updates)?;
+        let pool_conditions = match conditions.max_threads {
+            Some(max_threads) if max_threads != self.pool.current_num_threads() => Some(Self::build_pool(max_threads)?),
+            _ => None,
+        };
+        let pool = pool_conditions.as_ref().unwrap_or(&self.pool);
+
+        pool.install(|| {
+            sources.into_par_iter().map(|SourcePrepared { name: source, strategy, signals, updates }| {
+                let source_replayed = ReplaySource { name: &source, strategy: &strategy, allocation: 1.0, signals, updates };
+                let (signal_results, settings) = self.backtest_source(&conditions, &rules, source_replayed, pool.current_num_threads())?;
+                let backtest_result = Self::backtest_eval_results(&conditions, settings, signal_results, conditions.account.is_some(), conditions.initial_balance())?;
+                Ok((source, backtest_result))
+            }).collect::<Result<HashMap<String, BacktestResult>, BacktestError>>()
+        })
+    }
+
+    /// Replays the signals of all sources on one account, so that they compete for its balance and margin in time order.
+    /// `allocations` are the shares of the equity every source sizes its positions with, without them each sizes with all of it.
+    pub fn backtest_portfolio_execute(&self, conditions: BacktestConditions, strategy_rules: &HashMap<String, StrategyRules>, signals: Vec<Signal>, allocations: Option<HashMap<String, f32>>, updates: Vec<SignalUpdate>) -> Result<PortfolioResult, BacktestError> {
+        let rules = Self::prepare_conditions(&conditions)?;
+        let mut sources = self.backtest_prepare(&conditions, strategy_rules, signals, updates)?;
+        sources.sort_by(|source_a, source_b| source_a.name.cmp(&source_b.name));
+        if let Some(allocations) = &allocations {
+            Self::validate_allocations(allocations, sources.iter().map(|source| source.name.as_str()))?;
+        }
+
+        let max_threads = conditions.max_threads.unwrap_or(self.pool.current_num_threads());
+        let sources_replayed = sources.iter_mut()
+            .map(|source| ReplaySource {
+                name: &source.name,
+                strategy: &source.strategy,
+                allocation: allocations.as_ref().map_or(1.0, |allocations| allocations[source.name.as_str()]),
+                signals: std::mem::take(&mut source.signals),
+                updates: std::mem::take(&mut source.updates),
+            })
+            .collect();
+        let results = self.backtest_replay(&conditions, &rules, sources_replayed, Account::shared(&conditions), max_threads)?;
+
+        let mut results_portfolio = Vec::new();
+        let mut results_sources = HashMap::new();
+        for (source, (signal_results, settings)) in sources.into_iter().zip(results) {
+            results_portfolio.extend(signal_results.iter().cloned());
+            let balance_start = conditions.initial_balance() * allocations.as_ref().map_or(1.0, |allocations| allocations[source.name.as_str()]);
+            results_sources.insert(source.name, Self::backtest_eval_results(&conditions, settings, signal_results, true, balance_start)?);
+        }
+        let settings = EngineSettings::from_conditions(&conditions, max_threads);
+        let portfolio = Self::backtest_eval_results(&conditions, settings, results_portfolio, true, conditions.initial_balance())?;
+        Ok(PortfolioResult { portfolio, sources: results_sources })
+    }
+
+    fn validate_allocations<'a>(allocations: &HashMap<String, f32>, sources: impl Iterator<Item = &'a str>) -> Result<(), BacktestError> {
+        for source in sources {
+            let allocation = allocations.get(source)
+                .ok_or_else(|| BacktestError::Argument(format!("Missing allocation for source '{}'", source)))?;
+            if allocation.is_nan() || *allocation < 0.0 || *allocation > 1.0 {
+                return Err(BacktestError::Argument(format!("Allocation of source '{}' has to be between 0 and 1", source)));
+            }
+        }
+        if allocations.values().sum::<f32>() > 1.0 + 1e-6 {
+            return Err(BacktestError::Argument("Allocations add up to more than 1".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Prices of `symbol` for the checks from Python. Strategies work on the last price, so a symbol without prices is
+    /// rejected here instead of in every strategy.
+    fn symbol_prices<'a>(price_manager: &'a PriceManager, symbol: &str) -> Result<PriceSlice<'a>, BacktestError> {
+        match price_manager.prices.get(symbol) {
+            Some(series) if !series.is_empty() => Ok(series.as_slice()),
+            _ => Err(BacktestError::missing_price(symbol)),
+        }
+    }
+
+    pub fn check_filter_extern(&self, strategy_rules: StrategyRules, signal: Signal) -> Result<SignalResult, BacktestError> {
+        let strategy = StrategyManager::convert_rules_to_strategy(&strategy_rules)?;
+        signal.validate()?;
+        let price_manager = self.read_prices()?;
+        let prices = Self::symbol_prices(&price_manager, &signal.symbol)?;
         let mut signal_result = SignalResult::new(signal);
-        self.signal_check_init(&strategy, &mut signal_result, &prices);
-        signal_result
+        Self::signal_check_init(&strategy, &mut signal_result, &prices);
+        Ok(signal_result)
+    }
+
+    pub fn check_entry_extern(&self, strategy_rules: StrategyRules, mut signal_result: SignalResult) -> Result<SignalResult, BacktestError> {
+        let strategy = StrategyManager::convert_rules_to_strategy(&strategy_rules)?;
+        signal_result.signal.validate()?;
+        let price_manager = self.read_prices()?;
+        let prices = Self::symbol_prices(&price_manager, &signal_result.signal.symbol)?;
+        Self::signal_check_open(&strategy, &Slippage::default(), &IntrabarStep::default(), &mut signal_result, &prices, None)?;
+        Ok(signal_result)
+    }
+
+    pub fn check_exit_extern(&self, strategy_rules: StrategyRules, mut signal_result: SignalResult) -> Result<SignalResult, BacktestError> {
+        let strategy = StrategyManager::convert_rules_to_strategy(&strategy_rules)?;
+        signal_result.signal.validate()?;
+        let price_manager = self.read_prices()?;
+        let prices = Self::symbol_prices(&price_manager, &signal_result.signal.symbol)?;
+        Self::signal_check_close(&strategy, &Slippage::default(), &IntrabarStep::default(), &mut signal_result, &prices)?;
+        Ok(signal_result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::account::AccountRules;
+    use crate::evaluation::fixtures::{self, SOURCE};
+    use crate::evaluation::intrabar::IntrabarRules;
+    use crate::evaluation::swap::{SwapRate, SwapRules};
+    use crate::evaluation::trading_calendar::{CalendarRules, SessionRules};
+    use crate::prices::price_ohlc::PriceOhlc;
+    use crate::prices::price_series::PriceKind;
+    use crate::strategies::scale_out::ScaleOutRules;
+    use crate::strategies::strategy::StrategyRules;
+
+    fn backtest_with(backtesting_signals: &BacktestingSignals, conditions: BacktestConditions, signals: Vec<Signal>) -> BacktestResult {
+        let rules = fixtures::rules_of_source(fixtures::strategy_rules());
+        let mut results = backtesting_signals.backtest_execute(conditions, &rules, signals, Vec::new()).unwrap();
+        results.remove(SOURCE).unwrap()
+    }
+
+    /// Signal results of replaying `signals` of `SOURCE` with `rules`, before they are evaluated.
+    fn replay(backtesting_signals: &BacktestingSignals, conditions: BacktestConditions, rules: StrategyRules, signals: Vec<Signal>, updates: Vec<SignalUpdate>) -> Vec<SignalResult> {
+        let condition_rules = BacktestingSignals::prepare_conditions(&conditions).unwrap();
+        let mut sources = backtesting_signals.backtest_prepare(&conditions, &fixtures::rules_of_source(rules), signals, updates).unwrap();
+        let SourcePrepared { name, strategy, signals, updates } = sources.pop().unwrap();
+        let source = ReplaySource { name: &name, strategy: &strategy, allocation: 1.0, signals, updates };
+        backtesting_signals.backtest_source(&conditions, &condition_rules, source, 1).unwrap().0
+    }
+
+    fn order_events(signal_result: &SignalResult) -> Vec<&str> {
+        signal_result.order_events.iter().map(|event| event.kind.as_str()).collect()
+    }
+
+    fn backtest(backtesting_signals: &BacktestingSignals, signals: Vec<Signal>) -> BacktestResult {
+        backtest_with(backtesting_signals, fixtures::conditions(), signals)
     }
 
-    pub fn check_entry_extern(&self, strategy_rules: StrategyRules, mut signal_result: SignalResult) -> SignalResult {
-        let strategy = StrategyManager::convert_rules_to_strategy(&strategy_rules).expect("Failed to convert rules to strategy");
-        let price_manager = self.price_manager.read().unwrap();
-        let prices = price_manager.prices.get(&signal_result.signal.symbol).expect("Prices not found");
-        self.signal_check_open(&strategy, &mut signal_result, &prices);
-        signal_result
+    #[test]
+    fn instances_backtest_their_own_prices() {
+        let winning = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.2, 1.2]));
+        let losing = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.0, 1.0]));
+        let empty = BacktestingSignals::new(Arc::new(RwLock::new(PriceManager::new())), 1).unwrap();
+        let signals = vec![fixtures::signal("buy", 1.0, 1.2, 0)];
+
+        let result = backtest(&winning, signals.clone());
+        assert_eq!(result.num_trades, 1);
+        assert!((result.profit - 0.1).abs() < 1e-5);
+        let result = backtest(&losing, signals.clone());
+        assert_eq!(result.num_trades, 1);
+        assert!((result.profit + 0.1).abs() < 1e-5);
+        assert_eq!(backtest(&empty, signals).num_trades, 0);
+    }
+
+    #[test]
+    fn instances_backtest_concurrently() {
+        let backtesting_signals: Vec<BacktestingSignals> = [1.2, 1.0].iter()
+            .map(|&close| fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, close, close])))
+            .collect();
+        let profits: Vec<f32> = std::thread::scope(|scope| {
+            let handles: Vec<_> = backtesting_signals.iter()
+                .map(|backtesting_signals| scope.spawn(|| backtest(backtesting_signals, vec![fixtures::signal("buy", 1.0, 1.2, 0)]).profit))
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        assert!((profits[0] - 0.1).abs() < 1e-5);
+        assert!((profits[1] + 0.1).abs() < 1e-5);
+    }
+
+    /// Conditions whose only session lasts the first two minutes of every day.
+    fn conditions_short_session(end_of_session: &str) -> BacktestConditions {
+        BacktestConditions {
+            calendar: Some(CalendarRules {
+                timezone: None,
+                trading_days: None,
+                session: Some(SessionRules { open: "00:00".to_string(), close: "00:02".to_string() }),
+                sessions: None,
+                holidays: None,
+                end_of_session: Some(end_of_session.to_string()),
+            }),
+            ..fixtures::conditions()
+        }
     }
 
-    pub fn check_exit_extern(&self, strategy_rules: StrategyRules, mut signal_result: SignalResult) -> SignalResult {
-        let strategy = StrategyManager::convert_rules_to_strategy(&strategy_rules).expect("Failed to convert rules to strategy");
-        let price_manager = self.price_manager.read().unwrap();
-        let prices = price_manager.prices.get(&signal_result.signal.symbol).expect("Prices not found");
-        self.signal_check_close(&strategy, &mut signal_result, &prices);
-        signal_result
+    #[test]
+    fn positions_close_or_hold_at_the_end_of_the_session() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.15, 1.2, 1.2]));
+        let signals = vec![fixtures::signal("buy", 1.0, 1.2, 0)];
+
+        let result = backtest_with(&backtesting_signals, conditions_short_session("close"), signals.clone());
+        assert_eq!(result.num_trades, 1);
+        assert_eq!(result.positions[0].time_stamp_close, Some(fixtures::TUESDAY + fixtures::MINUTE));
+        assert!(result.profit.abs() < 1e-5);
+
+        let result = backtest_with(&backtesting_signals, conditions_short_session("hold"), signals);
+        assert_eq!(result.positions[0].time_stamp_close, Some(fixtures::TUESDAY + 3 * fixtures::MINUTE));
+        assert!((result.profit - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn signals_outside_of_sessions_are_ignored() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.1, 1.2, 1.2]));
+        let result = backtest_with(&backtesting_signals, conditions_short_session("hold"), vec![fixtures::signal("buy", 1.0, 1.2, 2)]);
+        assert_eq!(result.num_trades, 0);
+        let result = backtest(&backtesting_signals, vec![fixtures::signal("buy", 1.0, 1.2, 2)]);
+        assert_eq!(result.num_trades, 1);
+    }
+
+    #[test]
+    fn results_carry_the_engine_settings_they_ran_with() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.2, 1.2]));
+        let conditions = BacktestConditions {
+            max_price_delay_seconds: Some(30),
+            entry_timeout_minutes: Some(5.0),
+            max_threads: Some(2),
+            ..fixtures::conditions()
+        };
+        // The first price after the signal is a minute away, longer than the signal may wait for it
+        let result = backtest_with(&backtesting_signals, conditions, vec![fixtures::signal("buy", 1.0, 1.2, 0)]);
+        assert_eq!(result.num_trades, 0);
+        assert_eq!((result.settings.max_price_delay_seconds, result.settings.entry_timeout_minutes, result.settings.max_threads), (30, 5.0, 2));
+    }
+
+    fn is_missing_price<T>(result: Result<T, BacktestError>) -> bool {
+        matches!(result, Err(BacktestError::MissingPrice(_)))
+    }
+
+    #[test]
+    fn checks_reject_symbols_without_prices() {
+        let backtesting_signals = fixtures::backtester(PriceSeries::new(PriceKind::Ohlc));
+        let signal = fixtures::signal("buy", 1.0, 1.2, 0);
+        assert!(is_missing_price(backtesting_signals.check_filter_extern(fixtures::strategy_rules(), signal.clone())));
+        assert!(is_missing_price(backtesting_signals.check_entry_extern(fixtures::strategy_rules(), SignalResult::new(signal.clone()))));
+        assert!(is_missing_price(backtesting_signals.check_exit_extern(fixtures::strategy_rules(), SignalResult::new(signal.clone()))));
+
+        let signal = Signal { symbol: "GBPUSD".to_string(), ..signal };
+        assert!(is_missing_price(backtesting_signals.check_filter_extern(fixtures::strategy_rules(), signal)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn checks_report_missing_state_of_signal_results() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.2]));
+        let mut signal_result = SignalResult::new(fixtures::signal("buy", 1.0, 1.2, 0));
+        signal_result.position.opened = true;
+        let result = backtesting_signals.check_exit_extern(fixtures::strategy_rules(), signal_result.clone());
+        assert!(matches!(result, Err(BacktestError::StrategyState(_))));
+
+        signal_result.position.closed = true;
+        let result = BacktestingSignals::backtest_eval_results(&fixtures::conditions(), EngineSettings::from_conditions(&fixtures::conditions(), 1), vec![signal_result], false, 1000.0);
+        assert!(matches!(result, Err(BacktestError::StrategyState(_))));
+    }
+
+    #[test]
+    fn invalid_rules_and_conditions_are_reported() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.2]));
+        let signals = vec![fixtures::signal("buy", 1.0, 1.2, 0)];
+        let rules = fixtures::rules_of_source(StrategyRules::new("immediat", &[], "fixed_tp", &[]));
+        let result = backtesting_signals.backtest_execute(fixtures::conditions(), &rules, signals.clone(), Vec::new());
+        assert!(matches!(result, Err(BacktestError::StrategyConfig(_))));
+
+        let conditions = BacktestConditions { contract_sizes: HashMap::new(), ..fixtures::conditions() };
+        let rules = fixtures::rules_of_source(fixtures::strategy_rules());
+        let result = backtesting_signals.backtest_execute(conditions, &rules, signals, Vec::new());
+        assert!(matches!(result, Err(BacktestError::Conditions(_))));
+    }
+
+    #[test]
+    fn profit_adds_up_the_partial_fills() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.2, 1.3, 1.3]));
+        let rules = fixtures::rules_of_source(fixtures::strategy_rules().with_scale_out(ScaleOutRules::new(&[0.5], None)));
+        let mut signal = fixtures::signal("buy", 1.0, 1.2, 0);
+        signal.take_profit.push(1.3);
+        let mut results = backtesting_signals.backtest_execute(fixtures::conditions(), &rules, vec![signal], Vec::new()).unwrap();
+        let result = results.remove(SOURCE).unwrap();
+
+        let fills: Vec<(u64, f32, f32)> = result.positions[0].fills.iter().map(|fill| (fill.time_stamp, fill.price, fill.size)).collect();
+        assert_eq!(fills, vec![(fixtures::TUESDAY + 2 * fixtures::MINUTE, 1.2, 0.5), (fixtures::TUESDAY + 3 * fixtures::MINUTE, 1.3, 0.5)]);
+        assert_eq!(result.positions[0].price_close, Some(1.3));
+        assert!((result.profit - 0.15).abs() < 1e-5);
+    }
+
+    #[test]
+    fn entries_without_a_positive_size_are_rejected() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.2, 1.2]));
+        for lots in [0.0, -1.0, f32::NAN] {
+            let rules = fixtures::rules_of_source(fixtures::strategy_rules().with_sizer("fixed_lots", &[("lots", lots)]));
+            let signals = vec![fixtures::signal("buy", 1.0, 1.2, 0)];
+            let mut results = backtesting_signals.backtest_execute(fixtures::conditions(), &rules, signals, Vec::new()).unwrap();
+            let result = results.remove(SOURCE).unwrap();
+            assert_eq!((result.num_trades, result.ignored_counts.invalid_size), (0, 1));
+        }
+    }
+
+    #[test]
+    fn starting_balance_is_given_once() {
+        let account = AccountRules { balance: 1000.0, compounding: None, margin_call_level: None, stop_out_level: None };
+        let conditions = BacktestConditions { initial_equity: Some(500.0), account: Some(account.clone()), ..fixtures::conditions() };
+        assert!(matches!(BacktestingSignals::prepare_conditions(&conditions), Err(BacktestError::Conditions(_))));
+
+        let conditions = BacktestConditions { account: Some(account), ..fixtures::conditions() };
+        assert!(BacktestingSignals::prepare_conditions(&conditions).is_ok());
+        assert_eq!(conditions.initial_balance(), 1000.0);
+        assert_eq!(BacktestConditions { initial_equity: Some(500.0), ..fixtures::conditions() }.initial_balance(), 500.0);
+        assert_eq!(fixtures::conditions().initial_balance(), fixtures::conditions().max_margin);
+    }
+
+    fn conditions_account(balance: f32, leverage: f32) -> BacktestConditions {
+        BacktestConditions {
+            account: Some(AccountRules { balance, compounding: None, margin_call_level: None, stop_out_level: None }),
+            leverage: Some(leverage),
+            ..fixtures::conditions()
+        }
+    }
+
+    #[test]
+    fn positions_beyond_the_margin_call_level_are_rejected() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.2, 1.2]));
+        // Each position holds 1.1 of margin, the second one would leave the equity of 2 below the used margin
+        let signals = vec![fixtures::signal("buy", 1.0, 1.2, 0), fixtures::signal("buy", 1.0, 1.2, 0)];
+        let result = backtest_with(&backtesting_signals, conditions_account(2.0, 1.0), signals);
+        assert_eq!((result.num_trades, result.ignored_counts.missing_margin), (1, 1));
+        assert!((result.balance - 2.1).abs() < 1e-5);
+        assert!((result.margin.utilization - 0.55).abs() < 1e-5);
+    }
+
+    #[test]
+    fn positions_are_stopped_out_below_the_stop_out_level() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.0, 0.95, 1.2, 1.2]));
+        let rules = fixtures::rules_of_source(fixtures::strategy_rules().with_sizer("fixed_lots", &[("lots", 10.0)]));
+        let signals = vec![fixtures::signal("buy", 0.5, 1.2, 0)];
+        // 10 lots hold 1.1 of margin, at 0.95 the equity of 2 - 1.5 falls below half of it
+        let mut results = backtesting_signals.backtest_execute(conditions_account(2.0, 10.0), &rules, signals, Vec::new()).unwrap();
+        let result = results.remove(SOURCE).unwrap();
+        let position = &result.positions[0];
+        assert!(position.stopped_out);
+        assert_eq!((position.time_stamp_close, position.price_close), (Some(fixtures::TUESDAY + 3 * fixtures::MINUTE), Some(0.95)));
+        assert!((result.profit + 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stop_losses_gapped_through_fill_at_the_open() {
+        let backtesting_signals = fixtures::backtester(fixtures::bars(&[
+            (1.1, 1.1, 1.1, 1.1), (1.1, 1.1, 1.1, 1.1), (0.9, 0.95, 0.85, 0.9), (0.9, 0.9, 0.9, 0.9), (0.9, 0.9, 0.9, 0.9),
+        ]));
+        let result = backtest(&backtesting_signals, vec![fixtures::signal("buy", 1.0, 1.2, 0)]);
+        let position = &result.positions[0];
+        assert_eq!((position.time_stamp_close, position.price_close), (Some(fixtures::TUESDAY + 2 * fixtures::MINUTE), Some(0.9)));
+    }
+
+    #[test]
+    fn swaps_are_charged_for_positions_held_over_the_rollover() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.1, 1.2, 1.2, 1.2]));
+        let conditions = BacktestConditions {
+            swaps: Some(SwapRules {
+                rates: HashMap::from([(fixtures::SYMBOL.to_string(), SwapRate { long: -0.5, short: 0.25 })]),
+                rollover_time: Some("00:02".to_string()),
+                triple_swap_day: Some("Tue".to_string()),
+            }),
+            ..fixtures::conditions()
+        };
+        let result = backtest_with(&backtesting_signals, conditions, vec![fixtures::signal("buy", 1.0, 1.2, 0)]);
+        let position = &result.positions[0];
+        assert_eq!(position.time_stamp_close, Some(fixtures::TUESDAY + 3 * fixtures::MINUTE));
+        assert_eq!(position.swap, -1.5);
+        assert!((result.profit + 1.4).abs() < 1e-5);
+    }
+
+    /// Closes of 1.1 with one of 1.0 at minute 4, so that the volatility at minute 10 is 0.1, followed by `closes`.
+    fn bars_volatile(closes: &[f32]) -> PriceSeries {
+        let mut bars = vec![1.1; 11];
+        bars[4] = 1.0;
+        bars.extend_from_slice(closes);
+        fixtures::flat_bars(&bars)
+    }
+
+    fn breakout(limit_factor: Option<f32>) -> StrategyRules {
+        let mut entry_parameters = vec![("entry_factor", 1.0), ("vol_timeframe", 60.0)];
+        entry_parameters.extend(limit_factor.map(|limit_factor| ("limit_factor", limit_factor)));
+        StrategyRules::new("breakout", &entry_parameters, "fixed_tp", &[("tp_factor", 0.0), ("vol_timeframe", 60.0)])
+    }
+
+    #[test]
+    fn stop_orders_fill_once_reached() {
+        // The stop at 1.21 is gapped through by the open of 1.25
+        let backtesting_signals = fixtures::backtester(bars_volatile(&[1.15, 1.25, 1.25, 1.3, 1.3]));
+        let results = replay(&backtesting_signals, fixtures::conditions(), breakout(None), vec![fixtures::signal("buy", 1.0, 1.3, 10)], Vec::new());
+        assert_eq!(order_events(&results[0]), ["placed", "filled"]);
+        let position = &results[0].position;
+        assert_eq!((position.time_stamp_open, position.price_open), (Some(fixtures::TUESDAY + 12 * fixtures::MINUTE), Some(1.25)));
+    }
+
+    #[test]
+    fn stop_limit_orders_are_triggered_before_they_fill() {
+        // The stop at 1.21 triggers beyond the limit at 1.265, which fills on the way back
+        let backtesting_signals = fixtures::backtester(bars_volatile(&[1.15, 1.3, 1.25, 1.25, 1.4, 1.4]));
+        let results = replay(&backtesting_signals, fixtures::conditions(), breakout(Some(1.5)), vec![fixtures::signal("buy", 1.0, 1.4, 10)], Vec::new());
+        assert_eq!(order_events(&results[0]), ["placed", "triggered", "filled"]);
+        assert_eq!(results[0].position.price_open, Some(1.25));
+    }
+
+    #[test]
+    fn amendments_in_a_row_are_merged() {
+        // The band of the limit order moves with every rising close and stays below the prices
+        let closes: Vec<f32> = (0..100).map(|minute| 1.1 + minute as f32 * 0.001).collect();
+        let backtesting_signals = fixtures::backtester(bars_volatile(&closes));
+        let rules = StrategyRules::new("bollinger", &[("std_dev_factor", 2.0), ("period_minutes", 60.0)], "fixed_tp", &[("tp_factor", 0.0), ("vol_timeframe", 60.0)])
+            .with_entry_timeout(90.0);
+        let results = replay(&backtesting_signals, fixtures::conditions(), rules, vec![fixtures::signal("buy", 1.0, 1.5, 10)], Vec::new());
+        assert_eq!(order_events(&results[0]), ["placed", "amended", "expired"]);
+        let amended = &results[0].order_events[1];
+        // Amended on every price until the entry timeout of 90 minutes, the last amendment is the order that expires
+        assert_eq!(amended.time_stamp, fixtures::TUESDAY + 100 * fixtures::MINUTE);
+        assert_eq!(amended.order.price, results[0].order_events[2].order.price);
+    }
+
+    #[test]
+    fn orders_expire_with_their_time_in_force() {
+        let backtesting_signals = fixtures::backtester(bars_volatile(&[1.15, 1.15, 1.15, 1.15]));
+        let signals = || vec![fixtures::signal("buy", 1.0, 1.3, 10)];
+
+        let results = replay(&backtesting_signals, fixtures::conditions(), breakout(None).with_time_in_force("ioc"), signals(), Vec::new());
+        assert_eq!(order_events(&results[0]), ["placed", "expired"]);
+        assert_eq!(results[0].order_events[1].time_stamp, fixtures::TUESDAY + 10 * fixtures::MINUTE);
+
+        let conditions = BacktestConditions {
+            calendar: Some(CalendarRules {
+                timezone: None,
+                trading_days: None,
+                session: Some(SessionRules { open: "00:00".to_string(), close: "00:12".to_string() }),
+                sessions: None,
+                holidays: None,
+                end_of_session: Some("hold".to_string()),
+            }),
+            ..fixtures::conditions()
+        };
+        let results = replay(&backtesting_signals, conditions, breakout(None).with_time_in_force("day"), signals(), Vec::new());
+        assert_eq!(order_events(&results[0]), ["placed", "expired"]);
+        assert_eq!(results[0].order_events[1].time_stamp, fixtures::TUESDAY + 11 * fixtures::MINUTE);
+        assert!(results[0].position.ignored.no_entry);
+
+        // Without a time in force orders wait until the end of the prices
+        let results = replay(&backtesting_signals, fixtures::conditions(), breakout(None), signals(), Vec::new());
+        assert_eq!(order_events(&results[0]), ["placed", "expired"]);
+        assert_eq!(results[0].order_events[1].time_stamp, fixtures::TUESDAY + 13 * fixtures::MINUTE);
+        assert!(results[0].position.ignored.end_of_day);
+    }
+
+    #[test]
+    fn stop_losses_moved_by_the_stop_manager_are_checked() {
+        let backtesting_signals = fixtures::backtester(fixtures::bars(&[
+            (1.1, 1.1, 1.1, 1.1), (1.25, 1.25, 1.25, 1.25), (1.15, 1.15, 1.15, 1.15), (1.12, 1.12, 1.08, 1.1), (1.1, 1.1, 1.1, 1.1), (1.1, 1.1, 1.1, 1.1),
+        ]));
+        let rules = fixtures::strategy_rules().with_stop_manager("break_even", &[("trigger_r", 1.0)]);
+        let results = replay(&backtesting_signals, fixtures::conditions(), rules, vec![fixtures::signal("buy", 1.0, 1.5, 0)], Vec::new());
+        let position = &results[0].position;
+        let stop_changes: Vec<(u64, f32, &str)> = position.stop_changes.iter().map(|change| (change.time_stamp, change.stop_loss, change.reason.as_str())).collect();
+        assert_eq!(stop_changes, [(fixtures::TUESDAY + fixtures::MINUTE, 1.1, "break_even")]);
+        assert_eq!((position.time_stamp_close, position.price_close), (Some(fixtures::TUESDAY + 3 * fixtures::MINUTE), Some(1.1)));
+    }
+
+    #[test]
+    fn scale_out_keeps_a_stop_loss_the_stop_manager_tightened() {
+        let backtesting_signals = fixtures::backtester(fixtures::bars(&[
+            (1.1, 1.1, 1.1, 1.1), (1.25, 1.25, 1.25, 1.25), (1.33, 1.33, 1.33, 1.33), (1.25, 1.25, 1.15, 1.2), (1.2, 1.2, 1.2, 1.2),
+        ]));
+        // Profit lock moves the stop loss over the open price before the first level at 1.3 is filled, break even must not move it back
+        let rules = fixtures::strategy_rules()
+            .with_stop_manager("profit_lock", &[("step_r", 1.0), ("lock_fraction", 0.5)])
+            .with_scale_out(ScaleOutRules::new(&[0.5], Some("break_even")));
+        let mut signal = fixtures::signal("buy", 1.0, 1.3, 0);
+        signal.take_profit.push(1.5);
+        let results = replay(&backtesting_signals, fixtures::conditions(), rules, vec![signal], Vec::new());
+        let position = &results[0].position;
+        let stop_changes: Vec<(u64, &str)> = position.stop_changes.iter().map(|change| (change.time_stamp, change.reason.as_str())).collect();
+        assert_eq!(stop_changes, [(fixtures::TUESDAY + fixtures::MINUTE, "profit_lock"), (fixtures::TUESDAY + 2 * fixtures::MINUTE, "profit_lock")]);
+        assert!(position.stop_changes.iter().all(|change| change.stop_loss > 1.1));
+        assert_eq!(position.fills[0].price, 1.3);
+        assert_eq!(position.time_stamp_close, Some(fixtures::TUESDAY + 3 * fixtures::MINUTE));
+        assert!((position.price_close.unwrap() - 1.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn bars_reaching_stop_loss_and_exit_are_resolved_by_the_intrabar_policy() {
+        let bars = || fixtures::bars(&[(1.1, 1.1, 1.1, 1.1), (1.1, 1.3, 0.9, 1.1), (1.1, 1.1, 1.1, 1.1), (1.1, 1.1, 1.1, 1.1)]);
+        // Finer prices of the ambiguous bar reach the take profit after 20 seconds and the stop loss after 40
+        let mut finer = PriceSeries::new(PriceKind::Ohlc);
+        for (seconds, close) in [(0, 1.1), (20, 1.25), (40, 0.9)] {
+            finer.push(&PriceOhlc::new(fixtures::TUESDAY + fixtures::MINUTE + seconds * 1000, close, close, close, close));
+        }
+        let backtesting_signals = fixtures::backtester_of(HashMap::from([(fixtures::SYMBOL.to_string(), bars()), ("FINER".to_string(), finer)]));
+        let intrabar = |policy: &str| BacktestConditions {
+            intrabar: Some(IntrabarRules {
+                policy: policy.to_string(),
+                symbols: Some(HashMap::from([(fixtures::SYMBOL.to_string(), "FINER".to_string())])),
+            }),
+            ..fixtures::conditions()
+        };
+
+        for (conditions, price_close) in [(fixtures::conditions(), 1.0), (intrabar("optimistic"), 1.2), (intrabar("drill_down"), 1.2)] {
+            let result = backtest_with(&backtesting_signals, conditions, vec![fixtures::signal("buy", 1.0, 1.2, 0)]);
+            assert_eq!(result.positions[0].price_close, Some(price_close));
+            assert_eq!(result.ambiguous_trades, 1);
+        }
+    }
+
+    #[test]
+    fn maximum_margin_caps_the_positions_open_at_the_same_time() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.1, 1.2, 1.1, 1.1, 1.2, 1.2]));
+        // Three positions opened by minute 2 close at minute 3, the one opened at minute 4 finds their margin released
+        let signals = || [0, 1, 2, 4].map(|minute| fixtures::signal("buy", 1.0, 1.2, minute)).to_vec();
+        let conditions = BacktestConditions { max_margin: 2.5, ..fixtures::conditions() };
+
+        let result = backtest_with(&backtesting_signals, conditions.clone(), signals());
+        assert_eq!((result.num_trades, result.ignored_counts.missing_margin), (3, 1));
+        assert!((result.margin.max - 2.2).abs() < 1e-5);
+        assert!((result.margin.utilization - 0.88).abs() < 1e-5);
+
+        let conditions = BacktestConditions { margin_rates: Some(HashMap::from([(fixtures::SYMBOL.to_string(), 0.5)])), ..conditions };
+        let result = backtest_with(&backtesting_signals, conditions, signals());
+        assert_eq!((result.num_trades, result.ignored_counts.missing_margin), (4, 0));
+        assert!((result.margin.max - 1.65).abs() < 1e-5);
+        assert!(result.positions.iter().all(|position| position.margin == Some(0.55)));
+    }
+
+    fn signal_of(source: &str, minute: u64) -> Signal {
+        Signal { source: source.to_string(), ..fixtures::signal("buy", 1.0, 1.2, minute) }
+    }
+
+    fn rules_of_sources(rules: StrategyRules) -> HashMap<String, StrategyRules> {
+        HashMap::from([("A".to_string(), rules.clone()), ("B".to_string(), rules)])
+    }
+
+    #[test]
+    fn sources_of_a_portfolio_compete_for_the_margin() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.1, 1.2, 1.2]));
+        let conditions = BacktestConditions { max_margin: 2.5, ..fixtures::conditions() };
+        let rules = rules_of_sources(fixtures::strategy_rules());
+        let signals = || vec![signal_of("A", 0), signal_of("A", 1), signal_of("B", 2)];
+
+        let results = backtesting_signals.backtest_execute(conditions.clone(), &rules, signals(), Vec::new()).unwrap();
+        assert_eq!((results["A"].num_trades, results["B"].num_trades), (2, 1));
+
+        let result = backtesting_signals.backtest_portfolio_execute(conditions, &rules, signals(), None, Vec::new()).unwrap();
+        assert_eq!((result.portfolio.num_trades, result.portfolio.ignored_counts.missing_margin), (2, 1));
+        assert_eq!((result.sources["A"].num_trades, result.sources["B"].ignored_counts.missing_margin), (2, 1));
+    }
+
+    #[test]
+    fn sources_size_and_report_with_their_allocation() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.5, 1.5, 1.6, 1.6]));
+        let conditions = BacktestConditions {
+            account: Some(AccountRules { balance: 12.0, compounding: None, margin_call_level: None, stop_out_level: None }),
+            ..fixtures::conditions()
+        };
+        let rules = rules_of_sources(fixtures::strategy_rules().with_sizer("equity_percent", &[("percent", 100.0)]));
+        let signals = vec![Signal { take_profit: vec![1.6], ..signal_of("A", 0) }, Signal { take_profit: vec![1.6], ..signal_of("B", 0) }];
+        let allocations = HashMap::from([("A".to_string(), 0.5), ("B".to_string(), 0.25)]);
+        let result = backtesting_signals.backtest_portfolio_execute(conditions, &rules, signals, Some(allocations), Vec::new()).unwrap();
+
+        // A sizes with 6 of the equity and B with 3, both use all of their share as margin
+        let (a, b) = (&result.sources["A"], &result.sources["B"]);
+        assert_eq!((a.positions[0].lots, b.positions[0].lots), (Some(4.0), Some(2.0)));
+        assert!((a.balance - 6.4).abs() < 1e-5 && (b.balance - 3.2).abs() < 1e-5);
+        assert!((a.margin.utilization - 1.0).abs() < 1e-5 && (b.margin.utilization - 1.0).abs() < 1e-5);
+        assert!((result.portfolio.balance - 12.6).abs() < 1e-5);
+        assert!((result.portfolio.margin.utilization - 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn allocations_are_validated() {
+        let sources = ["A", "B"];
+        let allocations = |a: f32, b: Option<f32>| {
+            let mut allocations = HashMap::from([("A".to_string(), a)]);
+            allocations.extend(b.map(|b| ("B".to_string(), b)));
+            BacktestingSignals::validate_allocations(&allocations, sources.into_iter())
+        };
+        assert!(allocations(0.6, Some(0.4)).is_ok());
+        assert!(allocations(0.0, Some(0.0)).is_ok());
+        assert!(matches!(allocations(0.6, None), Err(BacktestError::Argument(_))));
+        assert!(allocations(-0.1, Some(0.4)).is_err());
+        assert!(allocations(f32::NAN, Some(0.4)).is_err());
+        assert!(allocations(0.7, Some(0.4)).is_err());
+    }
+
+    fn signal_with_id(id: &str, minute: u64) -> Signal {
+        Signal { id: Some(id.to_string()), ..fixtures::signal("buy", 1.0, 1.5, minute) }
+    }
+
+    fn update(id: &str, kind: &str, stop_loss: Option<f32>, minute: u64) -> SignalUpdate {
+        SignalUpdate { signal_id: id.to_string(), kind: kind.to_string(), stop_loss, time_stamp: fixtures::TUESDAY + minute * fixtures::MINUTE }
+    }
+
+    fn updates_applied(signal_result: &SignalResult) -> Vec<(&str, bool)> {
+        signal_result.updates.iter().map(|event| (event.update.kind.as_str(), event.applied)).collect()
+    }
+
+    #[test]
+    fn updates_move_the_stop_loss_and_close_positions() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.2, 1.15, 1.3, 1.3, 1.3]));
+        let signals = vec![signal_with_id("moved", 0), signal_with_id("closed", 0)];
+        let updates = vec![
+            update("moved", "move_stop_loss", Some(1.15), 1),
+            update("closed", "close", None, 3),
+            update("closed", "move_stop_loss", Some(1.2), 3),
+        ];
+        let mut results = replay(&backtesting_signals, fixtures::conditions(), fixtures::strategy_rules(), signals, updates);
+        results.sort_by_key(|result| result.signal.id.clone());
+
+        let (closed, moved) = (&results[0], &results[1]);
+        assert_eq!(updates_applied(moved), [("move_stop_loss", true)]);
+        assert_eq!(moved.position.stop_changes[0].reason, "signal_update");
+        assert_eq!((moved.position.time_stamp_close, moved.position.price_close), (Some(fixtures::TUESDAY + 2 * fixtures::MINUTE), Some(1.15)));
+
+        // The stop loss of a position closed by an earlier update stays in place
+        assert_eq!(updates_applied(closed), [("close", true), ("move_stop_loss", false)]);
+        assert_eq!((closed.position.time_stamp_close, closed.position.price_close), (Some(fixtures::TUESDAY + 3 * fixtures::MINUTE), Some(1.3)));
+    }
+
+    #[test]
+    fn updates_cancel_signals_waiting_for_their_entry_only() {
+        let backtesting_signals = fixtures::backtester(bars_volatile(&[1.15, 1.15, 1.15, 1.15]));
+        let signals = vec![Signal { id: Some("pending".to_string()), ..fixtures::signal("buy", 1.0, 1.3, 10) }];
+        let results = replay(&backtesting_signals, fixtures::conditions(), breakout(None), signals, vec![update("pending", "cancel", None, 12)]);
+        assert_eq!(updates_applied(&results[0]), [("cancel", true)]);
+        assert_eq!(order_events(&results[0]), ["placed", "cancelled"]);
+        assert!(results[0].position.ignored.no_entry);
+
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1, 1.1, 1.1]));
+        let results = replay(&backtesting_signals, fixtures::conditions(), fixtures::strategy_rules(), vec![signal_with_id("open", 0)], vec![update("open", "cancel", None, 1)]);
+        assert_eq!(updates_applied(&results[0]), [("cancel", false)]);
+        assert!(results[0].position.opened);
+    }
+
+    #[test]
+    fn updates_have_to_follow_a_known_signal() {
+        let backtesting_signals = fixtures::backtester(fixtures::flat_bars(&[1.1, 1.1]));
+        let rules = fixtures::rules_of_source(fixtures::strategy_rules());
+        let invalid = [
+            (vec![signal_with_id("a", 1)], vec![update("b", "close", None, 1)]),
+            (vec![signal_with_id("a", 1)], vec![update("a", "close", None, 0)]),
+            (vec![signal_with_id("a", 1), signal_with_id("a", 2)], Vec::new()),
+        ];
+        for (signals, updates) in invalid {
+            let result = backtesting_signals.backtest_execute(fixtures::conditions(), &rules, signals, updates);
+            assert!(matches!(result, Err(BacktestError::Signal(_))));
+        }
+    }
+}
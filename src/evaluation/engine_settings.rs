@@ -0,0 +1,82 @@
+use dict_derive::IntoPyObject;
+
+use crate::strategies::strategy::Strategy;
+use super::backtest_conditions::BacktestConditions;
+
+const MAX_PRICE_DELAY_SECONDS: u64 = 60*15;
+const ENTRY_TIMEOUT_MINUTES: f32 = 20.0;
+
+/// Engine settings a backtest ran with. Strategy rules take precedence over the conditions, missing values fall back to the defaults.
+#[derive(Clone, Debug, IntoPyObject)]
+pub struct EngineSettings {
+    pub max_price_delay_seconds: u64,
+    pub entry_timeout_minutes: f32,
+    pub max_threads: usize,
+}
+
+impl EngineSettings {
+    pub fn new(conditions: &BacktestConditions, strategy: &Strategy, max_threads: usize) -> Self {
+        EngineSettings {
+            max_price_delay_seconds: conditions.max_price_delay_seconds.unwrap_or(MAX_PRICE_DELAY_SECONDS),
+            entry_timeout_minutes: strategy.entry_timeout_minutes.or(conditions.entry_timeout_minutes).unwrap_or(ENTRY_TIMEOUT_MINUTES),
+            max_threads,
+        }
+    }
+
+    /// Settings of results that span several strategies, e.g. of a portfolio.
+    pub fn from_conditions(conditions: &BacktestConditions, max_threads: usize) -> Self {
+        EngineSettings {
+            max_price_delay_seconds: conditions.max_price_delay_seconds.unwrap_or(MAX_PRICE_DELAY_SECONDS),
+            entry_timeout_minutes: conditions.entry_timeout_minutes.unwrap_or(ENTRY_TIMEOUT_MINUTES),
+            max_threads,
+        }
+    }
+
+    pub fn entry_timeout_ms(&self) -> u64 {
+        (self.entry_timeout_minutes * 60.0 * 1000.0) as u64
+    }
+
+    pub fn validate_entry_timeout(entry_timeout_minutes: Option<f32>) -> Result<(), &'static str> {
+        match entry_timeout_minutes {
+            Some(minutes) if !(minutes >= 0.0 && minutes.is_finite()) => Err("Invalid entry_timeout_minutes"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+    use crate::strategies::strategy::StrategyManager;
+
+    #[test]
+    fn strategy_rules_take_precedence_over_the_conditions() {
+        let strategy = StrategyManager::convert_rules_to_strategy(&fixtures::strategy_rules()).unwrap();
+        let settings = EngineSettings::new(&fixtures::conditions(), &strategy, 2);
+        assert_eq!((settings.max_price_delay_seconds, settings.entry_timeout_minutes, settings.max_threads), (MAX_PRICE_DELAY_SECONDS, ENTRY_TIMEOUT_MINUTES, 2));
+
+        let conditions = BacktestConditions {
+            max_price_delay_seconds: Some(60),
+            entry_timeout_minutes: Some(5.0),
+            ..fixtures::conditions()
+        };
+        let settings = EngineSettings::new(&conditions, &strategy, 1);
+        assert_eq!((settings.max_price_delay_seconds, settings.entry_timeout_minutes), (60, 5.0));
+        assert_eq!(settings.entry_timeout_ms(), 5 * 60 * 1000);
+
+        let strategy = StrategyManager::convert_rules_to_strategy(&fixtures::strategy_rules().with_entry_timeout(90.0)).unwrap();
+        assert_eq!(EngineSettings::new(&conditions, &strategy, 1).entry_timeout_minutes, 90.0);
+        assert_eq!(EngineSettings::from_conditions(&conditions, 1).entry_timeout_minutes, 5.0);
+    }
+
+    #[test]
+    fn entry_timeout_has_to_be_finite_and_not_negative() {
+        assert!(EngineSettings::validate_entry_timeout(None).is_ok());
+        assert!(EngineSettings::validate_entry_timeout(Some(0.0)).is_ok());
+        for invalid in [-1.0, f32::NAN, f32::INFINITY] {
+            assert!(EngineSettings::validate_entry_timeout(Some(invalid)).is_err());
+        }
+        assert!(StrategyManager::convert_rules_to_strategy(&fixtures::strategy_rules().with_entry_timeout(-1.0)).is_err());
+    }
+}
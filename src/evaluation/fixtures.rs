@@ -0,0 +1,108 @@
+//! Conditions, strategies, signals and prices shared by the tests of the backtest engine.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::prices::price_manager::PriceManager;
+use crate::prices::price_ohlc::PriceOhlc;
+use crate::prices::price_series::{PriceKind, PriceSeries};
+use crate::strategies::sizer::sizer::SizingContext;
+use crate::strategies::strategy::StrategyRules;
+use super::backtest_conditions::BacktestConditions;
+use super::backtesting_signals::BacktestingSignals;
+use super::signal::{Signal, SignalResult};
+
+pub const SYMBOL: &str = "EURUSD";
+pub const SOURCE: &str = "source";
+pub const MINUTE: u64 = 60_000;
+/// 2024-01-02 00:00 UTC, a Tuesday.
+pub const TUESDAY: u64 = 1_704_153_600_000;
+
+/// Conditions with a contract size of 1 and no costs, so the profit of a position is its price difference per lot.
+pub fn conditions() -> BacktestConditions {
+    BacktestConditions {
+        max_margin: 1000.0,
+        commission: 0.0,
+        lot_size: 1.0,
+        initial_equity: None,
+        account: None,
+        contract_sizes: HashMap::from([(SYMBOL.to_string(), 1)]),
+        leverage: None,
+        margin_rates: None,
+        spreads: None,
+        slippage: None,
+        swaps: None,
+        intrabar: None,
+        calendar: None,
+        max_price_delay_seconds: None,
+        entry_timeout_minutes: None,
+        max_threads: None,
+    }
+}
+
+/// Immediate entry at market and an exit at the take profit of the signal.
+pub fn strategy_rules() -> StrategyRules {
+    StrategyRules::new("immediate", &[], "fixed_tp", &[("tp_factor", 0.0), ("vol_timeframe", 60.0)])
+}
+
+pub fn rules_of_source(rules: StrategyRules) -> HashMap<String, StrategyRules> {
+    HashMap::from([(SOURCE.to_string(), rules)])
+}
+
+/// Signal of `SOURCE` on `SYMBOL` given `minute` minutes after `TUESDAY`.
+pub fn signal(action: &str, stop_loss: f32, take_profit: f32, minute: u64) -> Signal {
+    Signal {
+        id: None,
+        symbol: SYMBOL.to_string(),
+        action: action.to_string(),
+        stop_loss,
+        take_profit: vec![take_profit],
+        time_stamp: TUESDAY + minute * MINUTE,
+        source: SOURCE.to_string(),
+    }
+}
+
+/// Signal with `stop_loss` whose position opened at `price_open`, its take profit is the open price as well.
+pub fn opened(action: &str, price_open: f32, stop_loss: f32) -> SignalResult {
+    let mut signal_result = SignalResult::new(signal(action, stop_loss, price_open, 0));
+    signal_result.position.price_open = Some(price_open);
+    signal_result
+}
+
+/// Buy signal with stop loss 1.0 and take profit 1.2 whose position opened at `price_open`.
+pub fn opened_at(price_open: f32) -> SignalResult {
+    let mut signal_result = SignalResult::new(signal("buy", 1.0, 1.2, 0));
+    signal_result.position.price_open = Some(price_open);
+    signal_result
+}
+
+/// Sizing of an account with 10000 of equity, a lot size of 1 and a contract size of 100000.
+pub fn sizing_context() -> SizingContext {
+    SizingContext { equity: 10_000.0, lot_size: 1.0, contract_size: 100_000.0 }
+}
+
+/// One bar per minute from `TUESDAY` on, given as open, high, low and close with ask equal to bid.
+pub fn bars(prices: &[(f32, f32, f32, f32)]) -> PriceSeries {
+    let mut series = PriceSeries::new(PriceKind::Ohlc);
+    for (minute, &(open, high, low, close)) in prices.iter().enumerate() {
+        series.push(&PriceOhlc::new(TUESDAY + minute as u64 * MINUTE, open, high, low, close));
+    }
+    series
+}
+
+/// Bars that open, move and close at the same price.
+pub fn flat_bars(closes: &[f32]) -> PriceSeries {
+    bars(&closes.iter().map(|&close| (close, close, close, close)).collect::<Vec<_>>())
+}
+
+/// Backtester with its own price storage holding `series` for `SYMBOL`.
+pub fn backtester(series: PriceSeries) -> BacktestingSignals {
+    backtester_of(HashMap::from([(SYMBOL.to_string(), series)]))
+}
+
+/// Backtester with its own price storage holding the series of several symbols.
+pub fn backtester_of(prices: HashMap<String, PriceSeries>) -> BacktestingSignals {
+    let mut price_manager = PriceManager::new();
+    price_manager.replace_prices(prices);
+    BacktestingSignals::new(Arc::new(RwLock::new(price_manager)), 1).unwrap()
+}
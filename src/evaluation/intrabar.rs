@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use dict_derive::FromPyObject;
+
+use crate::algorithms::Algorithms;
+use crate::prices::price::{Ohlc, Price, Tick};
+use crate::prices::price_slice::PriceSlice;
+use super::signal::SignalResult;
+
+/// Intrabar resolution as given in the backtest conditions, `symbols` maps a symbol to the symbol of its finer prices.
+#[derive(Clone, Debug, FromPyObject)]
+pub struct IntrabarRules {
+    pub policy: String,
+    pub symbols: Option<HashMap<String, String>>,
+}
+
+/// Decides whether the stop loss or the exit came first when a price reaches both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntrabarPolicy {
+    #[default]
+    Pessimistic,
+    Optimistic,
+    OpenDistance,
+    DrillDown,
+}
+
+impl IntrabarPolicy {
+    fn parse(policy: &str) -> Result<Self, String> {
+        match policy {
+            "pessimistic" => Ok(IntrabarPolicy::Pessimistic),
+            "optimistic" => Ok(IntrabarPolicy::Optimistic),
+            "open_distance" => Ok(IntrabarPolicy::OpenDistance),
+            "drill_down" => Ok(IntrabarPolicy::DrillDown),
+            _ => Err(format!("Invalid intrabar policy '{}', expected 'pessimistic', 'optimistic', 'open_distance' or 'drill_down'", policy)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Intrabar {
+    pub policy: IntrabarPolicy,
+    symbols: HashMap<String, String>,
+}
+
+impl Intrabar {
+    pub fn new(rules: Option<&IntrabarRules>) -> Result<Self, String> {
+        let rules = match rules {
+            Some(rules) => rules,
+            None => return Ok(Intrabar::default()),
+        };
+        let policy = IntrabarPolicy::parse(&rules.policy)?;
+        let symbols = rules.symbols.clone().unwrap_or_default();
+        if policy == IntrabarPolicy::DrillDown && symbols.is_empty() {
+            return Err("Intrabar policy 'drill_down' needs the symbols of the finer prices".to_string());
+        }
+        Ok(Intrabar { policy, symbols })
+    }
+
+    /// Symbol whose prices are drilled down into for the prices of `symbol`.
+    pub fn drill_down_symbol(&self, symbol: &str) -> Option<&str> {
+        match self.policy {
+            IntrabarPolicy::DrillDown => self.symbols.get(symbol).map(String::as_str),
+            _ => None,
+        }
+    }
+}
+
+/// Intrabar policy for one price of a replay, with the finer prices within it for drill down.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IntrabarStep<'a> {
+    policy: IntrabarPolicy,
+    prices: Option<PriceSlice<'a>>,
+}
+
+impl<'a> IntrabarStep<'a> {
+    /// Step for the price from `time_stamp_from` up to `time_stamp_to`, `prices` are all finer prices of the symbol.
+    pub fn new(policy: IntrabarPolicy, prices: Option<&PriceSlice<'a>>, time_stamp_from: u64, time_stamp_to: u64) -> Self {
+        let prices = prices.map(|prices| {
+            let time_stamps = prices.time_stamps();
+            let start = time_stamps.partition_point(|&time_stamp| time_stamp < time_stamp_from);
+            let end = time_stamps.partition_point(|&time_stamp| time_stamp < time_stamp_to);
+            prices.slice(start..end)
+        });
+        IntrabarStep { policy, prices }
+    }
+
+    /// Whether the stop loss of `signal_result` was reached before `target` within `price`, which reaches both.
+    /// Drill down falls back to the pessimistic policy without finer prices that reach either of them.
+    pub fn stop_loss_first(&self, signal_result: &SignalResult, price: &impl Price, target: f32) -> bool {
+        match self.policy {
+            IntrabarPolicy::Pessimistic => true,
+            IntrabarPolicy::Optimistic => false,
+            IntrabarPolicy::OpenDistance => Self::stop_loss_closer(signal_result, price, target),
+            IntrabarPolicy::DrillDown => {
+                let prices = match &self.prices {
+                    Some(prices) => prices,
+                    None => return true,
+                };
+                for p in 0..prices.len() {
+                    let price = prices.at(p);
+                    match (Algorithms::check_stop_loss_hit(signal_result, &price), Self::target_reached(signal_result, &price, target)) {
+                        (true, true) => return Self::stop_loss_closer(signal_result, &price, target),
+                        (true, false) => return true,
+                        (false, true) => return false,
+                        (false, false) => {}
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    fn stop_loss_closer(signal_result: &SignalResult, price: &impl Price, target: f32) -> bool {
+        let price_type = if signal_result.signal.action == "buy" { (Tick::Bid, Ohlc::Open) } else { (Tick::Ask, Ohlc::Open) };
+        let open = price.get(&price_type);
+        (open - signal_result.stop_loss()).abs() <= (target - open).abs()
+    }
+
+    fn target_reached(signal_result: &SignalResult, price: &impl Price, target: f32) -> bool {
+        if signal_result.signal.action == "buy" {
+            price.get(&(Tick::Bid, Ohlc::High)) >= target
+        } else {
+            price.get(&(Tick::Ask, Ohlc::Low)) <= target
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures::{self, MINUTE, TUESDAY};
+
+    fn rules(policy: &str, symbols: Option<HashMap<String, String>>) -> IntrabarRules {
+        IntrabarRules { policy: policy.to_string(), symbols }
+    }
+
+    #[test]
+    fn policies_decide_without_finer_prices() {
+        let signal_result = SignalResult::new(fixtures::signal("buy", 1.0, 1.3, 0));
+        // The open at 1.05 is closer to the stop loss at 1.0 than to the target at 1.3
+        let series = fixtures::bars(&[(1.05, 1.35, 0.95, 1.1), (1.25, 1.35, 0.95, 1.1)]);
+        let (near_stop_loss, near_target) = (series.as_slice().at(0), series.as_slice().at(1));
+        let step = |policy| IntrabarStep::new(policy, None, TUESDAY, TUESDAY + MINUTE);
+
+        assert!(step(IntrabarPolicy::Pessimistic).stop_loss_first(&signal_result, &near_target, 1.3));
+        assert!(!step(IntrabarPolicy::Optimistic).stop_loss_first(&signal_result, &near_stop_loss, 1.3));
+        assert!(step(IntrabarPolicy::OpenDistance).stop_loss_first(&signal_result, &near_stop_loss, 1.3));
+        assert!(!step(IntrabarPolicy::OpenDistance).stop_loss_first(&signal_result, &near_target, 1.3));
+        assert!(step(IntrabarPolicy::DrillDown).stop_loss_first(&signal_result, &near_target, 1.3));
+    }
+
+    #[test]
+    fn drill_down_takes_the_first_finer_price_reaching_either() {
+        let signal_result = SignalResult::new(fixtures::signal("sell", 1.2, 1.0, 0));
+        let bar = fixtures::flat_bars(&[1.1]);
+        // Finer prices per minute, the bar from minute 1 up to 3 reaches the target first
+        let finer = fixtures::flat_bars(&[1.25, 1.1, 0.95, 1.25, 1.1]);
+        let step = |from: u64, to: u64| IntrabarStep::new(IntrabarPolicy::DrillDown, Some(&finer.as_slice()), TUESDAY + from * MINUTE, TUESDAY + to * MINUTE);
+
+        assert!(!step(1, 3).stop_loss_first(&signal_result, &bar.as_slice().at(0), 1.0));
+        assert!(step(0, 3).stop_loss_first(&signal_result, &bar.as_slice().at(0), 1.0));
+        assert!(step(4, 5).stop_loss_first(&signal_result, &bar.as_slice().at(0), 1.0));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert_eq!(Intrabar::new(None).unwrap().policy, IntrabarPolicy::Pessimistic);
+        assert!(Intrabar::new(Some(&rules("coin_flip", None))).is_err());
+        assert!(Intrabar::new(Some(&rules("drill_down", None))).is_err());
+
+        let intrabar = Intrabar::new(Some(&rules("drill_down", Some(HashMap::from([("EURUSD".to_string(), "EURUSD_M1".to_string())]))))).unwrap();
+        assert_eq!(intrabar.drill_down_symbol("EURUSD"), Some("EURUSD_M1"));
+        assert_eq!(intrabar.drill_down_symbol("GBPUSD"), None);
+        let intrabar = Intrabar::new(Some(&rules("optimistic", Some(HashMap::from([("EURUSD".to_string(), "EURUSD_M1".to_string())]))))).unwrap();
+        assert_eq!(intrabar.drill_down_symbol("EURUSD"), None);
+    }
+}
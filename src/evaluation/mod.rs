@@ -1,5 +1,17 @@
+pub mod account;
 pub mod backtest_conditions;
 pub mod backtest_result;
 pub mod backtesting_signals;
+pub mod engine_settings;
+#[cfg(test)]
+pub mod fixtures;
+pub mod intrabar;
+pub mod order;
 pub mod signal;
-pub mod position;
\ No newline at end of file
+pub mod signal_update;
+pub mod position;
+pub mod replay_state;
+pub mod session_event;
+pub mod slippage;
+pub mod swap;
+pub mod trading_calendar;
\ No newline at end of file
@@ -0,0 +1,228 @@
+use dict_derive::{FromPyObject, IntoPyObject};
+
+use crate::error::BacktestError;
+use crate::prices::price::{Ohlc, Price, Tick};
+
+/// How a fill is executed: market orders take the current price, limit orders a better one and stop orders a worse one.
+/// Stop limit orders turn into a limit order once their stop is reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderType {
+    Market,
+    Limit,
+    Stop,
+    StopLimit,
+}
+
+impl OrderType {
+    pub fn parse(order_type: &str) -> Result<Self, String> {
+        match order_type {
+            "market" => Ok(OrderType::Market),
+            "limit" => Ok(OrderType::Limit),
+            "stop" => Ok(OrderType::Stop),
+            "stop_limit" => Ok(OrderType::StopLimit),
+            _ => Err(format!("Invalid order type '{}'", order_type)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrderType::Market => "market",
+            OrderType::Limit => "limit",
+            OrderType::Stop => "stop",
+            OrderType::StopLimit => "stop_limit",
+        }
+    }
+}
+
+/// How long an entry order stays pending.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeInForce {
+    /// Until the entry timeout.
+    GoodTillCancelled,
+    /// Until the end of the session, or the entry timeout if it comes first.
+    Day,
+    /// Only on the price the order is placed on.
+    ImmediateOrCancel,
+}
+
+impl TimeInForce {
+    pub fn parse(time_in_force: &str) -> Result<Self, String> {
+        match time_in_force {
+            "gtc" => Ok(TimeInForce::GoodTillCancelled),
+            "day" => Ok(TimeInForce::Day),
+            "ioc" => Ok(TimeInForce::ImmediateOrCancel),
+            _ => Err(format!("Invalid time in force '{}', expected 'gtc', 'day' or 'ioc'", time_in_force)),
+        }
+    }
+}
+
+/// Entry order as requested by an entry strategy. `price` is the limit or stop price, `limit_price` the limit of a stop limit order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrderRequest {
+    pub order_type: OrderType,
+    pub price: Option<f32>,
+    pub limit_price: Option<f32>,
+}
+
+impl OrderRequest {
+    pub fn market() -> Self {
+        OrderRequest { order_type: OrderType::Market, price: None, limit_price: None }
+    }
+
+    pub fn limit(price: f32) -> Self {
+        OrderRequest { order_type: OrderType::Limit, price: Some(price), limit_price: None }
+    }
+
+    pub fn stop(price: f32) -> Self {
+        OrderRequest { order_type: OrderType::Stop, price: Some(price), limit_price: None }
+    }
+
+    pub fn stop_limit(price: f32, limit_price: f32) -> Self {
+        OrderRequest { order_type: OrderType::StopLimit, price: Some(price), limit_price: Some(limit_price) }
+    }
+}
+
+/// Pending entry order of a signal, `time_stamp` is the time it was placed or last amended.
+#[derive(Clone, Debug, FromPyObject, IntoPyObject)]
+pub struct Order {
+    pub order_type: String,
+    pub price: Option<f32>,
+    pub limit_price: Option<f32>,
+    pub time_stamp: u64,
+    pub triggered: bool,
+}
+
+impl Order {
+    pub fn new(request: &OrderRequest, time_stamp: u64) -> Self {
+        Order {
+            order_type: request.order_type.name().to_string(),
+            price: request.price,
+            limit_price: request.limit_price,
+            time_stamp,
+            triggered: false,
+        }
+    }
+
+    pub fn kind(&self) -> Result<OrderType, BacktestError> {
+        OrderType::parse(&self.order_type).map_err(BacktestError::StrategyState)
+    }
+
+    pub fn is_request(&self, request: &OrderRequest) -> bool {
+        self.order_type == request.order_type.name() && self.price == request.price && self.limit_price == request.limit_price
+    }
+
+    /// Price the order fills at on `price`, `None` while it is not reached. Prices opening beyond an order placed
+    /// before them fill at their open, as the order was already waiting when the price gapped.
+    pub fn fill(&mut self, action: &str, price: &impl Price) -> Result<Option<f32>, BacktestError> {
+        let buy = action == "buy";
+        let get = |ohlc: Ohlc| price.get(&(if buy { Tick::Ask } else { Tick::Bid }, ohlc));
+        let (open, high, low, close) = (get(Ohlc::Open), get(Ohlc::High), get(Ohlc::Low), get(Ohlc::Close));
+        let placed_before = self.time_stamp < price.ts();
+        let order_price = |price: Option<f32>| price.ok_or_else(|| BacktestError::StrategyState(format!("Price of {} order not set", self.order_type)));
+
+        let fill_limit = |limit: f32| match buy {
+            true if low <= limit => Some(if placed_before { limit.min(open) } else { limit }),
+            false if high >= limit => Some(if placed_before { limit.max(open) } else { limit }),
+            _ => None,
+        };
+        let fill_stop = |stop: f32| match buy {
+            true if high >= stop => Some(if placed_before { stop.max(open) } else { stop }),
+            false if low <= stop => Some(if placed_before { stop.min(open) } else { stop }),
+            _ => None,
+        };
+
+        match self.kind()? {
+            OrderType::Market => Ok(Some(close)),
+            OrderType::Limit => Ok(fill_limit(order_price(self.price)?)),
+            OrderType::Stop => Ok(fill_stop(order_price(self.price)?)),
+            OrderType::StopLimit => {
+                let limit = order_price(self.limit_price)?;
+                if self.triggered {
+                    return Ok(fill_limit(limit));
+                }
+                let price_fill = match fill_stop(order_price(self.price)?) {
+                    Some(price_fill) => price_fill,
+                    None => return Ok(None),
+                };
+                // A price beyond the limit leaves the order waiting as a limit order
+                self.triggered = true;
+                Ok(if buy { price_fill <= limit } else { price_fill >= limit }.then_some(price_fill))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, FromPyObject, IntoPyObject)]
+pub struct OrderEvent {
+    pub kind: String,
+    pub time_stamp: u64,
+    pub order: Order,
+}
+
+impl OrderEvent {
+    pub fn new(kind: &str, time_stamp: u64, order: &Order) -> Self {
+        OrderEvent {
+            kind: kind.to_string(),
+            time_stamp,
+            order: order.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price_ohlc::PriceOhlc;
+
+    fn bar(time_stamp: u64, open: f32, high: f32, low: f32, close: f32) -> PriceOhlc {
+        PriceOhlc::new(time_stamp, open, high, low, close)
+    }
+
+    #[test]
+    fn limit_orders_fill_at_their_price_or_a_better_open() {
+        let mut buy = Order::new(&OrderRequest::limit(1.0), 0);
+        assert_eq!(buy.fill("buy", &bar(0, 1.05, 1.1, 0.98, 1.0)).unwrap(), Some(1.0));
+        assert_eq!(buy.fill("buy", &bar(1, 0.95, 0.97, 0.9, 0.92)).unwrap(), Some(0.95));
+        assert_eq!(buy.fill("buy", &bar(1, 1.05, 1.1, 1.01, 1.02)).unwrap(), None);
+
+        let mut sell = Order::new(&OrderRequest::limit(1.1), 0);
+        assert_eq!(sell.fill("sell", &bar(1, 1.15, 1.2, 1.12, 1.13)).unwrap(), Some(1.15));
+        assert_eq!(sell.fill("sell", &bar(1, 1.05, 1.09, 1.0, 1.02)).unwrap(), None);
+    }
+
+    #[test]
+    fn stop_orders_fill_at_their_price_or_a_worse_open() {
+        let mut buy = Order::new(&OrderRequest::stop(1.1), 0);
+        assert_eq!(buy.fill("buy", &bar(0, 1.0, 1.2, 1.0, 1.15)).unwrap(), Some(1.1));
+        assert_eq!(buy.fill("buy", &bar(1, 1.2, 1.25, 1.18, 1.2)).unwrap(), Some(1.2));
+        assert_eq!(buy.fill("buy", &bar(1, 1.0, 1.05, 0.98, 1.0)).unwrap(), None);
+
+        let mut sell = Order::new(&OrderRequest::stop(1.0), 0);
+        assert_eq!(sell.fill("sell", &bar(1, 0.95, 0.96, 0.9, 0.92)).unwrap(), Some(0.95));
+        assert_eq!(Order::new(&OrderRequest::market(), 0).fill("sell", &bar(1, 0.95, 0.96, 0.9, 0.92)).unwrap(), Some(0.92));
+    }
+
+    #[test]
+    fn stop_limit_orders_wait_as_limit_orders_after_a_gap_beyond_the_limit() {
+        let mut order = Order::new(&OrderRequest::stop_limit(1.1, 1.15), 0);
+        assert_eq!(order.fill("buy", &bar(1, 1.0, 1.05, 0.98, 1.0)).unwrap(), None);
+        assert!(!order.triggered);
+        assert_eq!(order.fill("buy", &bar(2, 1.2, 1.25, 1.18, 1.2)).unwrap(), None);
+        assert!(order.triggered);
+        assert_eq!(order.fill("buy", &bar(3, 1.14, 1.16, 1.13, 1.15)).unwrap(), Some(1.14));
+
+        let mut order = Order::new(&OrderRequest::stop_limit(1.1, 1.15), 0);
+        assert_eq!(order.fill("buy", &bar(1, 1.12, 1.14, 1.12, 1.13)).unwrap(), Some(1.12));
+    }
+
+    #[test]
+    fn orders_without_their_prices_are_reported() {
+        let mut order = Order::new(&OrderRequest::limit(1.0), 0);
+        order.price = None;
+        assert!(matches!(order.fill("buy", &bar(1, 1.0, 1.0, 1.0, 1.0)), Err(BacktestError::StrategyState(_))));
+        order.order_type = "iceberg".to_string();
+        assert!(order.kind().is_err());
+        assert!(TimeInForce::parse("fok").is_err());
+        assert_eq!(TimeInForce::parse("day").unwrap(), TimeInForce::Day);
+    }
+}
@@ -2,8 +2,15 @@ use std::collections::HashMap;
 
 use dict_derive::{FromPyObject, IntoPyObject};
 
+use crate::error::BacktestError;
+
+/// Sizes below this are treated as fully closed, fractions of a position do not add up exactly in floating point.
+pub const SIZE_EPSILON: f32 = 1e-6;
+
 #[derive(Clone, Debug, FromPyObject, IntoPyObject)]
 pub struct PositionIgnored {
+    pub missing_margin: bool,
+    pub invalid_size: bool,
     pub price_gap: bool,
     pub no_entry: bool,
     pub end_of_day: bool,
@@ -12,6 +19,8 @@ pub struct PositionIgnored {
 impl PositionIgnored {
     pub fn new() -> Self {
         PositionIgnored {
+            missing_margin: false,
+            invalid_size: false,
             price_gap: false,
             no_entry: false,
             end_of_day: false,
@@ -19,6 +28,32 @@ impl PositionIgnored {
     }
 }
 
+/// Closed part of a position, `size` is the fraction of the whole position.
+#[derive(Clone, Debug, FromPyObject, IntoPyObject)]
+pub struct PositionFill {
+    pub time_stamp: u64,
+    pub price: f32,
+    pub size: f32,
+}
+
+impl PositionFill {
+    pub fn new(time_stamp: u64, price: f32, size: f32) -> Self {
+        PositionFill {
+            time_stamp,
+            price,
+            size,
+        }
+    }
+}
+
+/// Stop loss moved by the strategy at `time_stamp`, `reason` names what moved it.
+#[derive(Clone, Debug, FromPyObject, IntoPyObject)]
+pub struct StopChange {
+    pub time_stamp: u64,
+    pub stop_loss: f32,
+    pub reason: String,
+}
+
 #[derive(Clone, Debug, FromPyObject, IntoPyObject)]
 pub struct Position {
     pub action: String,
@@ -27,10 +62,18 @@ pub struct Position {
     pub time_stamp_close: Option<u64>,
     pub price_open: Option<f32>,
     pub price_close: Option<f32>,
+    pub lots: Option<f32>,
+    pub margin: Option<f32>,
     pub delta: Option<f32>,
+    pub swap: f32,
+    pub stop_loss: Option<f32>,
+    pub stop_changes: Vec<StopChange>,
+    pub fills: Vec<PositionFill>,
     pub inited: bool,
     pub opened: bool,
     pub closed: bool,
+    pub stopped_out: bool,
+    pub intrabar_ambiguous: bool,
     pub strategy_attributes: HashMap<String, f32>,
 }
 
@@ -43,11 +86,37 @@ impl Position {
             time_stamp_close: None,
             price_open: None,
             price_close: None,
+            lots: None,
+            margin: None,
             delta: None,
+            swap: 0.0,
+            stop_loss: None,
+            stop_changes: Vec::new(),
+            fills: Vec::new(),
             inited: false,
             opened: false,
             closed: false,
+            stopped_out: false,
+            intrabar_ambiguous: false,
             strategy_attributes: HashMap::new(),
         }
     }
+
+    /// Fraction of the position that is not closed yet.
+    pub fn size_open(&self) -> f32 {
+        1.0 - self.fills.iter().map(|fill| fill.size).sum::<f32>()
+    }
+
+    pub fn move_stop(&mut self, time_stamp: u64, stop_loss: f32, reason: &str) {
+        self.stop_loss = Some(stop_loss);
+        self.stop_changes.push(StopChange {
+            time_stamp,
+            stop_loss,
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn strategy_attribute(&self, key: &str) -> Result<f32, BacktestError> {
+        self.strategy_attributes.get(key).copied().ok_or_else(|| BacktestError::missing_attribute(key))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,53 @@
+use crate::prices::price_slice::PriceSlice;
+use crate::strategies::strategy::Strategy;
+use super::backtest_conditions::BacktestConditions;
+use super::engine_settings::EngineSettings;
+use super::intrabar::Intrabar;
+use super::signal::{Signal, SignalResult};
+use super::signal_update::SignalUpdate;
+use super::slippage::Slippage;
+use super::swap::Swap;
+use super::trading_calendar::TradingCalendar;
+
+/// Everything a replay of one symbol for one source needs besides its prices. `allocation` is the fraction of the
+/// account equity the sizer of the source works with.
+pub struct ReplayContext<'a> {
+    pub conditions: &'a BacktestConditions,
+    pub calendar: &'a TradingCalendar,
+    pub slippage: &'a Slippage,
+    pub swap: &'a Swap,
+    pub intrabar: &'a Intrabar,
+    pub intrabar_prices: Option<PriceSlice<'a>>,
+    pub strategy: &'a Strategy,
+    pub settings: &'a EngineSettings,
+    pub source: &'a str,
+    pub symbol: &'a str,
+    pub contract_size: f32,
+    pub allocation: f32,
+}
+
+/// Signals of one symbol and source while its prices are replayed. Signals move from pending to running once
+/// the prices reach them and end up in the results when they are closed or ignored. Updates of the signals wait
+/// in time order until the prices reach them.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayState {
+    pub signals_pending: Vec<SignalResult>,
+    pub signals_running: Vec<SignalResult>,
+    pub signals_result: Vec<SignalResult>,
+    pub updates_pending: Vec<SignalUpdate>,
+}
+
+impl ReplayState {
+    pub fn new(signals: Vec<Signal>, mut updates: Vec<SignalUpdate>) -> Self {
+        updates.sort_by_key(|update| update.time_stamp);
+        ReplayState {
+            signals_pending: signals.into_iter().map(SignalResult::new).collect(),
+            updates_pending: updates,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.signals_pending.is_empty() && self.signals_running.is_empty()
+    }
+}
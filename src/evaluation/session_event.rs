@@ -0,0 +1,21 @@
+use dict_derive::IntoPyObject;
+
+use super::signal::SignalResult;
+
+/// Something that happened to a signal during a backtest session, `kind` is one of "open", "partial", "close" or "ignore".
+#[derive(Clone, Debug, IntoPyObject)]
+pub struct SessionEvent {
+    pub kind: String,
+    pub time_stamp: u64,
+    pub signal_result: SignalResult,
+}
+
+impl SessionEvent {
+    pub fn new(kind: &str, time_stamp: u64, signal_result: &SignalResult) -> Self {
+        SessionEvent {
+            kind: kind.to_string(),
+            time_stamp,
+            signal_result: signal_result.clone(),
+        }
+    }
+}
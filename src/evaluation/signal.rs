@@ -1,9 +1,14 @@
 use dict_derive::{FromPyObject, IntoPyObject};
 
+use crate::error::BacktestError;
+use super::order::{Order, OrderEvent};
 use super::position::Position;
+use super::signal_update::{SignalUpdate, SignalUpdateEvent};
 
+/// Signal of a provider, `id` links the updates that follow it.
 #[derive(Clone, Debug, FromPyObject, IntoPyObject)]
 pub struct Signal {
+    pub id: Option<String>,
     pub symbol: String,
     pub action: String,
     pub stop_loss: f32,
@@ -12,11 +17,23 @@ pub struct Signal {
     pub source: String,
 }
 
+impl Signal {
+    pub fn validate(&self) -> Result<(), BacktestError> {
+        if self.action != "buy" && self.action != "sell" {
+            return Err(BacktestError::Signal(format!("Invalid action '{}' for signal of symbol '{}', expected 'buy' or 'sell'", self.action, self.symbol)));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, FromPyObject, IntoPyObject)]
 pub struct SignalResult {
     pub signal: Signal,
     pub position: Position,
     pub ticket: Option<u32>,
+    pub order: Option<Order>,
+    pub order_events: Vec<OrderEvent>,
+    pub updates: Vec<SignalUpdateEvent>,
 }
 
 impl SignalResult {
@@ -25,6 +42,34 @@ impl SignalResult {
             position: Position::new(&signal.action),
             signal,
             ticket: None,
+            order: None,
+            order_events: Vec::new(),
+            updates: Vec::new(),
         }
     }
-}
\ No newline at end of file
+
+    /// The stop loss in effect, which the strategy may have moved away from the one of the signal.
+    pub fn stop_loss(&self) -> f32 {
+        self.position.stop_loss.unwrap_or(self.signal.stop_loss)
+    }
+
+    /// Moves the stop loss to `stop_loss` if it is tighter than the one in effect, a looser one is ignored.
+    pub fn tighten_stop(&mut self, time_stamp: u64, stop_loss: f32, reason: &str) {
+        let stop_loss_current = self.stop_loss();
+        let tighter = if self.signal.action == "buy" { stop_loss > stop_loss_current } else { stop_loss < stop_loss_current };
+        if tighter {
+            self.position.move_stop(time_stamp, stop_loss, reason);
+        }
+    }
+
+    /// Records `kind` happening to the entry order at `time_stamp`, signals without an order record nothing.
+    pub fn record_order_event(&mut self, kind: &str, time_stamp: u64) {
+        if let Some(order) = &self.order {
+            self.order_events.push(OrderEvent::new(kind, time_stamp, order));
+        }
+    }
+
+    pub fn record_update(&mut self, update: SignalUpdate, time_stamp: u64, applied: bool) {
+        self.updates.push(SignalUpdateEvent { update, time_stamp, applied });
+    }
+}
@@ -0,0 +1,65 @@
+use dict_derive::{FromPyObject, IntoPyObject};
+
+use crate::error::BacktestError;
+
+/// What a signal update asks for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignalUpdateKind {
+    /// Moves the stop loss of a pending or open signal.
+    MoveStopLoss(f32),
+    /// Closes an open position at market, a signal still waiting for its entry is cancelled.
+    Close,
+    /// Cancels a signal still waiting for its entry.
+    Cancel,
+}
+
+/// Follow up of the provider of a signal, e.g. "move SL to X", linked to the signal by its `id`.
+#[derive(Clone, Debug, FromPyObject, IntoPyObject)]
+pub struct SignalUpdate {
+    pub signal_id: String,
+    pub kind: String,
+    pub stop_loss: Option<f32>,
+    pub time_stamp: u64,
+}
+
+impl SignalUpdate {
+    pub fn kind(&self) -> Result<SignalUpdateKind, BacktestError> {
+        match self.kind.as_str() {
+            "move_stop_loss" => match self.stop_loss {
+                Some(stop_loss) if stop_loss.is_finite() => Ok(SignalUpdateKind::MoveStopLoss(stop_loss)),
+                _ => Err(BacktestError::Signal(format!("Update of signal '{}' to move the stop loss needs a stop_loss", self.signal_id))),
+            },
+            "close" => Ok(SignalUpdateKind::Close),
+            "cancel" => Ok(SignalUpdateKind::Cancel),
+            _ => Err(BacktestError::Signal(format!("Invalid kind '{}' for update of signal '{}', expected 'move_stop_loss', 'close' or 'cancel'", self.kind, self.signal_id))),
+        }
+    }
+}
+
+/// Update that reached a running signal at `time_stamp`, `applied` is false if it did not fit the state of the signal,
+/// e.g. cancelling a position that is already open.
+#[derive(Clone, Debug, FromPyObject, IntoPyObject)]
+pub struct SignalUpdateEvent {
+    pub update: SignalUpdate,
+    pub time_stamp: u64,
+    pub applied: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(kind: &str, stop_loss: Option<f32>) -> SignalUpdate {
+        SignalUpdate { signal_id: "signal".to_string(), kind: kind.to_string(), stop_loss, time_stamp: 0 }
+    }
+
+    #[test]
+    fn kinds_are_parsed_with_their_stop_loss() {
+        assert_eq!(update("move_stop_loss", Some(1.1)).kind().unwrap(), SignalUpdateKind::MoveStopLoss(1.1));
+        assert_eq!(update("close", None).kind().unwrap(), SignalUpdateKind::Close);
+        assert_eq!(update("cancel", Some(1.1)).kind().unwrap(), SignalUpdateKind::Cancel);
+        assert!(matches!(update("move_stop_loss", None).kind(), Err(BacktestError::Signal(_))));
+        assert!(update("move_stop_loss", Some(f32::INFINITY)).kind().is_err());
+        assert!(update("reverse", None).kind().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use dict_derive::FromPyObject;
+
+use crate::algorithms::Algorithms;
+use crate::prices::price::{Ohlc, Price, Tick};
+use crate::prices::price_slice::PriceSlice;
+use super::order::OrderType;
+use super::signal::SignalResult;
+
+#[derive(Clone, Debug, FromPyObject)]
+pub struct SlippageModelRules {
+    name: String,
+    parameters: HashMap<String, f32>,
+    /// Seed of the random model, kept out of the parameters as it has to be an exact integer.
+    seed: Option<u64>,
+}
+
+/// Slippage as given in the backtest conditions, one model per order type.
+#[derive(Clone, Debug, FromPyObject)]
+pub struct SlippageRules {
+    market: Option<SlippageModelRules>,
+    limit: Option<SlippageModelRules>,
+    stop: Option<SlippageModelRules>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SlippageModel {
+    Fixed { offset: f32 },
+    SpreadFraction { fraction: f32 },
+    Volatility { factor: f32, vol_timeframe: f32 },
+    Random { max_offset: f32, seed: u64 },
+}
+
+impl SlippageModel {
+    fn parse(rules: &SlippageModelRules) -> Result<Self, String> {
+        let parameter = |key: &str| {
+            let value = rules.parameters.get(key).copied().ok_or_else(|| format!("Missing {} for slippage model '{}'", key, rules.name))?;
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("Parameter {} of slippage model '{}' has to be finite and at least 0", key, rules.name));
+            }
+            Ok(value)
+        };
+        if rules.parameters.contains_key("seed") {
+            return Err(format!("Seed of slippage model '{}' has to be given as seed, not as a parameter", rules.name));
+        }
+        match rules.name.as_str() {
+            "fixed" => Ok(SlippageModel::Fixed { offset: parameter("offset")? }),
+            "spread_fraction" => Ok(SlippageModel::SpreadFraction { fraction: parameter("fraction")? }),
+            "volatility" => Ok(SlippageModel::Volatility { factor: parameter("factor")?, vol_timeframe: parameter("vol_timeframe")? }),
+            "random" => Ok(SlippageModel::Random {
+                max_offset: parameter("max_offset")?,
+                seed: rules.seed.unwrap_or(0),
+            }),
+            _ => Err(format!("Invalid slippage model name '{}'", rules.name)),
+        }
+    }
+
+    /// Offset in price units, always positive so that the caller decides the direction.
+    fn offset(&self, signal_result: &SignalResult, prices: &PriceSlice) -> f32 {
+        let last_price = prices.last().unwrap();
+        match *self {
+            SlippageModel::Fixed { offset } => offset,
+            SlippageModel::SpreadFraction { fraction } => {
+                let spread = last_price.get(&(Tick::Ask, Ohlc::Close)) - last_price.get(&(Tick::Bid, Ohlc::Close));
+                fraction * spread.max(0.0)
+            }
+            SlippageModel::Volatility { factor, vol_timeframe } => {
+                let price_type = (Tick::Bid, Ohlc::Close);
+                factor * Algorithms::calculate_volatility(&price_type, prices, vol_timeframe) * last_price.get(&price_type)
+            }
+            SlippageModel::Random { max_offset, seed } => {
+                // Seeded by the fill itself, so that results do not depend on the order signals are replayed in
+                let key = seed ^ signal_result.signal.time_stamp.rotate_left(21) ^ last_price.ts() ^ (signal_result.position.fills.len() as u64).rotate_left(42);
+                max_offset * Self::uniform(key)
+            }
+        }
+    }
+
+    /// Maps `key` to a number in [0, 1) with the SplitMix64 finalizer.
+    fn uniform(key: u64) -> f32 {
+        let mut z = key.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Moves fill prices against the position. Order types without a model are filled at their price.
+#[derive(Clone, Debug, Default)]
+pub struct Slippage {
+    market: Option<SlippageModel>,
+    limit: Option<SlippageModel>,
+    stop: Option<SlippageModel>,
+}
+
+impl Slippage {
+    pub fn new(rules: Option<&SlippageRules>) -> Result<Self, String> {
+        let rules = match rules {
+            Some(rules) => rules,
+            None => return Ok(Slippage::default()),
+        };
+        Ok(Slippage {
+            market: rules.market.as_ref().map(SlippageModel::parse).transpose()?,
+            limit: rules.limit.as_ref().map(SlippageModel::parse).transpose()?,
+            stop: rules.stop.as_ref().map(SlippageModel::parse).transpose()?,
+        })
+    }
+
+    /// Fill price for an order at `price`, `opening` tells whether the fill opens the position or closes part of it.
+    pub fn apply(&self, order_type: OrderType, signal_result: &SignalResult, prices: &PriceSlice, price: f32, opening: bool) -> f32 {
+        let model = match order_type {
+            OrderType::Market => &self.market,
+            OrderType::Limit | OrderType::StopLimit => &self.limit,
+            OrderType::Stop => &self.stop,
+        };
+        let model = match model {
+            Some(model) => model,
+            None => return price,
+        };
+        let offset = model.offset(signal_result, prices);
+        if (signal_result.signal.action == "buy") == opening {
+            price + offset
+        } else {
+            price - offset
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    fn model(name: &str, parameters: &[(&str, f32)], seed: Option<u64>) -> SlippageModelRules {
+        SlippageModelRules {
+            name: name.to_string(),
+            parameters: parameters.iter().map(|&(key, value)| (key.to_string(), value)).collect(),
+            seed,
+        }
+    }
+
+    fn fixed(offset: f32) -> Option<SlippageModelRules> {
+        Some(model("fixed", &[("offset", offset)], None))
+    }
+
+    #[test]
+    fn fills_move_against_the_position() {
+        let slippage = Slippage::new(Some(&SlippageRules { market: fixed(0.1), limit: fixed(0.2), stop: None })).unwrap();
+        let series = fixtures::flat_bars(&[1.0, 1.0]);
+        let prices = series.as_slice();
+        let buy = SignalResult::new(fixtures::signal("buy", 0.5, 2.0, 0));
+        let sell = SignalResult::new(fixtures::signal("sell", 2.0, 0.5, 0));
+
+        assert!((slippage.apply(OrderType::Market, &buy, &prices, 1.0, true) - 1.1).abs() < 1e-6);
+        assert!((slippage.apply(OrderType::Market, &buy, &prices, 1.0, false) - 0.9).abs() < 1e-6);
+        assert!((slippage.apply(OrderType::Market, &sell, &prices, 1.0, true) - 0.9).abs() < 1e-6);
+        assert!((slippage.apply(OrderType::StopLimit, &sell, &prices, 1.0, false) - 1.2).abs() < 1e-6);
+        assert_eq!(slippage.apply(OrderType::Stop, &buy, &prices, 1.0, false), 1.0);
+        assert_eq!(Slippage::new(None).unwrap().apply(OrderType::Market, &buy, &prices, 1.0, true), 1.0);
+    }
+
+    #[test]
+    fn random_offsets_depend_on_the_seed_only() {
+        let series = fixtures::flat_bars(&[1.0, 1.0]);
+        let prices = series.as_slice();
+        let signal_result = SignalResult::new(fixtures::signal("buy", 0.5, 2.0, 0));
+        let offset = |seed| SlippageModel::parse(&model("random", &[("max_offset", 0.01)], seed)).unwrap().offset(&signal_result, &prices);
+
+        assert_eq!(offset(Some(7)), offset(Some(7)));
+        assert_ne!(offset(Some(1 << 40)), offset(Some((1 << 40) + 1)));
+        assert_eq!(offset(None), offset(Some(0)));
+        for seed in 0..100 {
+            assert!((0.0..0.01).contains(&offset(Some(seed))));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_models() {
+        assert!(SlippageModel::parse(&model("fixed", &[("offset", 0.0)], None)).is_ok());
+        assert!(SlippageModel::parse(&model("fixed", &[], None)).is_err());
+        assert!(SlippageModel::parse(&model("fixed", &[("offset", -0.1)], None)).is_err());
+        assert!(SlippageModel::parse(&model("spread_fraction", &[("fraction", f32::NAN)], None)).is_err());
+        assert!(SlippageModel::parse(&model("random", &[("max_offset", -1.0)], Some(1))).is_err());
+        assert!(SlippageModel::parse(&model("random", &[("max_offset", 1.0), ("seed", 7.0)], None)).is_err());
+        assert!(SlippageModel::parse(&model("huge", &[], None)).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveTime, Weekday};
+use dict_derive::FromPyObject;
+
+use super::trading_calendar::TradingCalendar;
+
+/// Swap per lot and night in the account currency, negative values are costs.
+#[derive(Clone, Debug, FromPyObject)]
+pub struct SwapRate {
+    pub long: f32,
+    pub short: f32,
+}
+
+/// Swaps as given in the backtest conditions, the rollover time is local to the timezone of the trading calendar.
+#[derive(Clone, Debug, FromPyObject)]
+pub struct SwapRules {
+    pub rates: HashMap<String, SwapRate>,
+    pub rollover_time: Option<String>,
+    pub triple_swap_day: Option<String>,
+}
+
+/// Charges positions held over the rollover of a trading day, three times on the triple swap day to cover the weekend.
+#[derive(Clone, Debug)]
+pub struct Swap {
+    rates: HashMap<String, SwapRate>,
+    rollover_time: NaiveTime,
+    triple_swap_day: Weekday,
+}
+
+impl Swap {
+    pub fn new(rules: Option<&SwapRules>) -> Result<Self, String> {
+        let mut swap = Swap {
+            rates: HashMap::new(),
+            rollover_time: NaiveTime::MIN,
+            triple_swap_day: Weekday::Wed,
+        };
+        let rules = match rules {
+            Some(rules) => rules,
+            None => return Ok(swap),
+        };
+
+        swap.rates = rules.rates.clone();
+        if let Some(rollover_time) = &rules.rollover_time {
+            swap.rollover_time = NaiveTime::parse_from_str(rollover_time, "%H:%M").map_err(|_| format!("Invalid rollover time '{}', expected HH:MM", rollover_time))?;
+        }
+        if let Some(triple_swap_day) = &rules.triple_swap_day {
+            swap.triple_swap_day = triple_swap_day.parse().map_err(|_| format!("Invalid triple swap day '{}'", triple_swap_day))?;
+        }
+        Ok(swap)
+    }
+
+    /// Swap for `lots` of `symbol` held from `time_stamp_from` to `time_stamp_to`.
+    pub fn charge(&self, calendar: &TradingCalendar, symbol: &str, action: &str, lots: f32, time_stamp_from: u64, time_stamp_to: u64) -> f32 {
+        let rate = match self.rates.get(symbol) {
+            Some(rate) if action == "buy" => rate.long,
+            Some(rate) => rate.short,
+            None => return 0.0,
+        };
+        let nights: u32 = calendar.trading_days_passed(self.rollover_time, time_stamp_from, time_stamp_to).iter()
+            .map(|date| if date.weekday() == self.triple_swap_day { 3 } else { 1 })
+            .sum();
+        rate * lots * nights as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures::TUESDAY;
+
+    const HOUR: u64 = 3_600_000;
+
+    fn rules(rollover_time: Option<&str>, triple_swap_day: Option<&str>) -> SwapRules {
+        SwapRules {
+            rates: HashMap::from([("EURUSD".to_string(), SwapRate { long: -1.0, short: 0.5 })]),
+            rollover_time: rollover_time.map(String::from),
+            triple_swap_day: triple_swap_day.map(String::from),
+        }
+    }
+
+    #[test]
+    fn charges_every_rollover_and_three_times_on_the_triple_swap_day() {
+        let calendar = TradingCalendar::new(None).unwrap();
+        let swap = Swap::new(Some(&rules(None, None))).unwrap();
+        // Tuesday noon to Thursday noon passes the rollovers at midnight that end Tuesday and Wednesday, the triple swap day
+        let (from, to) = (TUESDAY + 12 * HOUR, TUESDAY + 60 * HOUR);
+        assert_eq!(swap.charge(&calendar, "EURUSD", "buy", 2.0, from, to), -8.0);
+        assert_eq!(swap.charge(&calendar, "EURUSD", "sell", 2.0, from, to), 4.0);
+        assert_eq!(swap.charge(&calendar, "EURUSD", "buy", 1.0, from, from + 24 * HOUR), -1.0);
+        assert_eq!(swap.charge(&calendar, "EURUSD", "buy", 1.0, from + 24 * HOUR, to), -3.0);
+        assert_eq!(swap.charge(&calendar, "EURUSD", "buy", 2.0, from, from + HOUR), 0.0);
+        assert_eq!(swap.charge(&calendar, "GBPUSD", "buy", 2.0, from, to), 0.0);
+        assert_eq!(Swap::new(None).unwrap().charge(&calendar, "EURUSD", "buy", 2.0, from, to), 0.0);
+    }
+
+    #[test]
+    fn rollover_time_and_triple_swap_day_can_be_set() {
+        let calendar = TradingCalendar::new(None).unwrap();
+        let swap = Swap::new(Some(&rules(Some("17:00"), Some("Fri")))).unwrap();
+        // Saturday and Sunday are no trading days, so Friday covers the weekend
+        assert_eq!(swap.charge(&calendar, "EURUSD", "buy", 1.0, TUESDAY + 12 * HOUR, TUESDAY + 18 * HOUR), -1.0);
+        assert_eq!(swap.charge(&calendar, "EURUSD", "buy", 1.0, TUESDAY + 3 * 24 * HOUR, TUESDAY + 6 * 24 * HOUR), -3.0);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(Swap::new(Some(&rules(Some("5pm"), None))).is_err());
+        assert!(Swap::new(Some(&rules(None, Some("Caturday")))).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeZone, Timelike, Weekday};
+use chrono_tz::Tz;
+use dict_derive::FromPyObject;
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+const DEFAULT_TRADING_DAYS: [Weekday; 5] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+
+#[derive(Clone, Debug, FromPyObject)]
+pub struct SessionRules {
+    pub open: String,
+    pub close: String,
+}
+
+/// Trading calendar as given in the backtest conditions. Times and dates are local to `timezone` and a session belongs
+/// to the day it opens, so a session from 17:00 to 17:00 opened on Sunday is the Sunday session.
+#[derive(Clone, Debug, FromPyObject)]
+pub struct CalendarRules {
+    pub timezone: Option<String>,
+    pub trading_days: Option<Vec<String>>,
+    pub session: Option<SessionRules>,
+    pub sessions: Option<HashMap<String, SessionRules>>,
+    pub holidays: Option<Vec<String>>,
+    pub end_of_session: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndOfSession {
+    Close,
+    Hold,
+}
+
+impl EndOfSession {
+    pub fn parse(end_of_session: &str) -> Result<Self, String> {
+        match end_of_session {
+            "close" => Ok(EndOfSession::Close),
+            "hold" => Ok(EndOfSession::Hold),
+            _ => Err(format!("Invalid end of session behavior '{}', expected 'close' or 'hold'", end_of_session)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Session {
+    open: i64,
+    length: i64,
+}
+
+impl Session {
+    const ALL_DAY: Session = Session { open: 0, length: MINUTES_PER_DAY };
+
+    /// A close at or before the open ends the session on the next day, equal times span the whole day.
+    fn parse(rules: &SessionRules) -> Result<Self, String> {
+        let open = Self::parse_minutes(&rules.open)?;
+        let close = Self::parse_minutes(&rules.close)?;
+        let length = (close - open).rem_euclid(MINUTES_PER_DAY);
+        Ok(Session {
+            open,
+            length: if length == 0 { MINUTES_PER_DAY } else { length },
+        })
+    }
+
+    fn parse_minutes(time: &str) -> Result<i64, String> {
+        let time = NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| format!("Invalid session time '{}', expected HH:MM", time))?;
+        Ok(time.hour() as i64 * 60 + time.minute() as i64)
+    }
+}
+
+/// Decides when symbols can be traded. Without rules every symbol trades around the clock from Monday to Friday in UTC
+/// and positions are closed at the end of each day.
+#[derive(Clone, Debug)]
+pub struct TradingCalendar {
+    timezone: Tz,
+    trading_days: HashSet<Weekday>,
+    session: Session,
+    sessions: HashMap<String, Session>,
+    holidays: HashSet<NaiveDate>,
+    pub end_of_session: EndOfSession,
+}
+
+impl TradingCalendar {
+    pub fn new(rules: Option<&CalendarRules>) -> Result<Self, String> {
+        let mut calendar = TradingCalendar {
+            timezone: Tz::UTC,
+            trading_days: HashSet::from(DEFAULT_TRADING_DAYS),
+            session: Session::ALL_DAY,
+            sessions: HashMap::new(),
+            holidays: HashSet::new(),
+            end_of_session: EndOfSession::Close,
+        };
+        let rules = match rules {
+            Some(rules) => rules,
+            None => return Ok(calendar),
+        };
+
+        if let Some(timezone) = &rules.timezone {
+            calendar.timezone = timezone.parse().map_err(|_| format!("Unknown timezone '{}'", timezone))?;
+        }
+        if let Some(trading_days) = &rules.trading_days {
+            calendar.trading_days = trading_days.iter()
+                .map(|day| day.parse().map_err(|_| format!("Invalid trading day '{}'", day)))
+                .collect::<Result<_, String>>()?;
+        }
+        if let Some(session) = &rules.session {
+            calendar.session = Session::parse(session)?;
+        }
+        for (symbol, session) in rules.sessions.iter().flatten() {
+            calendar.sessions.insert(symbol.clone(), Session::parse(session)?);
+        }
+        for holiday in rules.holidays.iter().flatten() {
+            let date = NaiveDate::parse_from_str(holiday, "%Y-%m-%d").map_err(|_| format!("Invalid holiday '{}', expected YYYY-MM-DD", holiday))?;
+            calendar.holidays.insert(date);
+        }
+        if let Some(end_of_session) = &rules.end_of_session {
+            calendar.end_of_session = EndOfSession::parse(end_of_session)?;
+        }
+        Ok(calendar)
+    }
+
+    /// Trading days that end at `time` in the calendar timezone after `time_stamp_from` and up to `time_stamp_to`.
+    /// A day ending at midnight ends at the start of the following date.
+    pub fn trading_days_passed(&self, time: NaiveTime, time_stamp_from: u64, time_stamp_to: u64) -> Vec<NaiveDate> {
+        let mut dates = Vec::new();
+        let local = |time_stamp: u64| self.timezone.timestamp_millis_opt(time_stamp as i64).single().map(|date_time| date_time.date_naive());
+        let (mut date, date_last) = match (local(time_stamp_from).and_then(|date| date.pred_opt()), local(time_stamp_to)) {
+            (Some(date), Some(date_last)) => (date, date_last),
+            _ => return dates,
+        };
+        while date <= date_last {
+            let end = if time == NaiveTime::MIN { date.succ_opt() } else { Some(date) };
+            let passed = end.and_then(|end| self.timezone.from_local_datetime(&end.and_time(time)).earliest())
+                .is_some_and(|date_time| date_time.timestamp_millis() > time_stamp_from as i64 && date_time.timestamp_millis() <= time_stamp_to as i64);
+            if passed && self.trading_days.contains(&date.weekday()) && !self.holidays.contains(&date) {
+                dates.push(date);
+            }
+            date = match date.succ_opt() {
+                Some(date) => date,
+                None => break,
+            };
+        }
+        dates
+    }
+
+    /// The opening day of the session of `symbol` that `time_stamp` falls into, `None` if the symbol is not traded at that time.
+    pub fn session(&self, symbol: &str, time_stamp: u64) -> Option<NaiveDate> {
+        let session = self.sessions.get(symbol).unwrap_or(&self.session);
+        let local = self.timezone.timestamp_millis_opt(time_stamp as i64).single()?.naive_local();
+        let minute = local.hour() as i64 * 60 + local.minute() as i64;
+        let (date, minutes_open) = if minute >= session.open {
+            (local.date(), minute - session.open)
+        } else {
+            (local.date().pred_opt()?, minute + MINUTES_PER_DAY - session.open)
+        };
+        if minutes_open >= session.length || !self.trading_days.contains(&date.weekday()) || self.holidays.contains(&date) {
+            return None;
+        }
+        Some(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60 * 1000;
+    /// 2024-01-02 00:00 UTC, a Tuesday.
+    const TUESDAY: u64 = 1_704_153_600_000;
+
+    fn rules() -> CalendarRules {
+        CalendarRules {
+            timezone: None,
+            trading_days: None,
+            session: None,
+            sessions: None,
+            holidays: None,
+            end_of_session: None,
+        }
+    }
+
+    fn session(open: &str, close: &str) -> SessionRules {
+        SessionRules { open: open.to_string(), close: close.to_string() }
+    }
+
+    fn date(day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+    }
+
+    #[test]
+    fn default_calendar_trades_weekdays_around_the_clock() {
+        let calendar = TradingCalendar::new(None).unwrap();
+        assert_eq!(calendar.session("EURUSD", TUESDAY), date(2));
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 24 * HOUR - 1), date(2));
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 4 * 24 * HOUR + 12 * HOUR), None);
+        assert_eq!(calendar.end_of_session, EndOfSession::Close);
+    }
+
+    #[test]
+    fn session_across_midnight_belongs_to_its_opening_day() {
+        let calendar = TradingCalendar::new(Some(&CalendarRules {
+            timezone: Some("America/New_York".to_string()),
+            trading_days: Some(["Sun", "Mon", "Tue", "Wed", "Thu"].map(String::from).to_vec()),
+            session: Some(session("17:00", "17:00")),
+            end_of_session: Some("hold".to_string()),
+            ..rules()
+        })).unwrap();
+        // New York is 5 hours behind UTC in January
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 5 * 24 * HOUR + 23 * HOUR), date(7));
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 6 * 24 * HOUR + 22 * HOUR - 1), date(7));
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 3 * 24 * HOUR + 21 * HOUR), date(4));
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 3 * 24 * HOUR + 22 * HOUR), None);
+        assert_eq!(calendar.end_of_session, EndOfSession::Hold);
+    }
+
+    #[test]
+    fn symbol_sessions_and_holidays() {
+        let calendar = TradingCalendar::new(Some(&CalendarRules {
+            sessions: Some(HashMap::from([("SPX".to_string(), session("09:30", "16:00"))])),
+            holidays: Some(vec!["2024-01-03".to_string()]),
+            ..rules()
+        })).unwrap();
+        assert_eq!(calendar.session("SPX", TUESDAY + 10 * HOUR), date(2));
+        assert_eq!(calendar.session("SPX", TUESDAY + 9 * HOUR), None);
+        assert_eq!(calendar.session("SPX", TUESDAY + 16 * HOUR), None);
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 20 * HOUR), date(2));
+        assert_eq!(calendar.session("SPX", TUESDAY + 34 * HOUR), None);
+        assert_eq!(calendar.session("EURUSD", TUESDAY + 34 * HOUR), None);
+    }
+
+    #[test]
+    fn trading_days_passed_counts_times_on_trading_days() {
+        let calendar = TradingCalendar::new(Some(&CalendarRules {
+            timezone: Some("America/New_York".to_string()),
+            ..rules()
+        })).unwrap();
+        let rollover = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+        // Friday 12:00 to Tuesday 12:00 in New York passes 17:00 on Friday, Saturday, Sunday and Monday
+        let friday_noon = TUESDAY + 3 * 24 * HOUR + 17 * HOUR;
+        assert_eq!(calendar.trading_days_passed(rollover, friday_noon, friday_noon + 4 * 24 * HOUR), vec![date(5).unwrap(), date(8).unwrap()]);
+        assert!(calendar.trading_days_passed(rollover, friday_noon, friday_noon + HOUR).is_empty());
+        // Midnight ends the trading day before it, Friday ends on Saturday and Sunday on Monday
+        assert_eq!(calendar.trading_days_passed(NaiveTime::MIN, friday_noon, friday_noon + 4 * 24 * HOUR), vec![date(5).unwrap(), date(8).unwrap()]);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let invalid = [
+            CalendarRules { timezone: Some("Mars/Base".to_string()), ..rules() },
+            CalendarRules { trading_days: Some(vec!["Funday".to_string()]), ..rules() },
+            CalendarRules { session: Some(session("25:00", "17:00")), ..rules() },
+            CalendarRules { holidays: Some(vec!["2024-13-01".to_string()]), ..rules() },
+            CalendarRules { end_of_session: Some("later".to_string()), ..rules() },
+        ];
+        for rules in invalid.iter() {
+            assert!(TradingCalendar::new(Some(rules)).is_err(), "{:?}", rules);
+        }
+    }
+}
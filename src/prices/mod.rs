@@ -1,4 +1,13 @@
 pub mod price;
+pub mod price_arrays;
+pub mod price_csv;
+pub mod price_info;
+pub mod price_ingestion;
 pub mod price_manager;
 pub mod price_ohlc;
-pub mod price_tick;
\ No newline at end of file
+pub mod price_resampler;
+pub mod price_series;
+pub mod price_slice;
+pub mod price_snapshot;
+pub mod price_tick;
+pub mod spread;
@@ -1,4 +1,3 @@
-
 #[derive(Debug, Clone)]
 pub enum Tick {
     Ask,
@@ -13,9 +12,23 @@ pub enum Ohlc {
     Close,
 }
 
+#[derive(Debug, Clone)]
+pub enum Auxiliary {
+    Sentiment,
+}
+
 pub type PriceType = (Tick, Ohlc);
 
 pub trait Price: Send + Sync {
     fn get(&self, price_type: &PriceType) -> f32;
     fn ts(&self) -> u64;
-}
\ No newline at end of file
+
+    fn get_auxiliary(&self, _auxiliary: &Auxiliary) -> Option<f32> {
+        None
+    }
+
+    /// Whether the price carries its own ask side, bars without it have ask equal to bid.
+    fn has_ask(&self) -> bool {
+        true
+    }
+}
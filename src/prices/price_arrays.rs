@@ -0,0 +1,111 @@
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::prelude::*;
+
+use super::price_series::{PriceKind, PriceSeries};
+
+const OHLC_COLUMNS: [&str; 4] = ["open", "high", "low", "close"];
+const OHLC_ASK_COLUMNS: [&str; 4] = ["ask_open", "ask_high", "ask_low", "ask_close"];
+
+/// Reads price columns from objects supporting the buffer protocol, like NumPy arrays, or from objects offering
+/// `to_numpy()`, like Arrow arrays and pandas series. Contiguous buffers are read element by element into the columns of
+/// a `PriceSeries`, so the values are copied once and 64 bit floats are narrowed on the way.
+pub struct PriceArrays;
+
+impl PriceArrays {
+    pub fn tick(py: Python, time_stamps: &PyAny, bid: &PyAny, ask: &PyAny, sentiment: Option<&PyAny>) -> Result<PriceSeries, String> {
+        let columns = vec![Self::values(py, bid, "bid")?, Self::values(py, ask, "ask")?];
+        Self::series(py, PriceKind::Tick, time_stamps, columns, sentiment)
+    }
+
+    pub fn ohlc(py: Python, time_stamps: &PyAny, bid: [&PyAny; 4], ask: Option<[&PyAny; 4]>) -> Result<PriceSeries, String> {
+        let mut columns = Vec::with_capacity(8);
+        for (array, name) in bid.into_iter().zip(OHLC_COLUMNS) {
+            columns.push(Self::values(py, array, name)?);
+        }
+        for (array, name) in ask.into_iter().flatten().zip(OHLC_ASK_COLUMNS) {
+            columns.push(Self::values(py, array, name)?);
+        }
+        Self::series(py, PriceKind::Ohlc, time_stamps, columns, None)
+    }
+
+    /// Reads a table with named columns, e.g. an Arrow record batch or a pandas data frame. Tables with a `bid` column
+    /// are read as ticks, all others as bars. Column names match the fields of `PriceTick` and `PriceOhlc`.
+    pub fn table(py: Python, table: &PyAny) -> Result<PriceSeries, String> {
+        let time_stamps = Self::column(table, "time_stamp").ok_or("Missing column 'time_stamp'")?;
+        if let Some(bid) = Self::column(table, "bid") {
+            let ask = Self::column(table, "ask").ok_or("Missing column 'ask'")?;
+            return Self::tick(py, time_stamps, bid, ask, Self::column(table, "sentiment"));
+        }
+
+        let mut bid = Vec::with_capacity(4);
+        for name in OHLC_COLUMNS {
+            bid.push(Self::column(table, name).ok_or(format!("Missing column '{}'", name))?);
+        }
+        let ask: Vec<&PyAny> = OHLC_ASK_COLUMNS.iter().filter_map(|name| Self::column(table, name)).collect();
+        let ask = match ask.len() {
+            0 => None,
+            4 => Some([ask[0], ask[1], ask[2], ask[3]]),
+            _ => return Err("Ask columns have to be given for open, high, low and close".to_string()),
+        };
+        Self::ohlc(py, time_stamps, [bid[0], bid[1], bid[2], bid[3]], ask)
+    }
+
+    fn series(py: Python, kind: PriceKind, time_stamps: &PyAny, columns: Vec<Vec<f32>>, sentiment: Option<&PyAny>) -> Result<PriceSeries, String> {
+        let time_stamps = Self::time_stamps(py, time_stamps)?;
+        let sentiment = sentiment.map(|array| Self::values(py, array, "sentiment")).transpose()?;
+        PriceSeries::from_columns(kind, time_stamps, columns, sentiment).map_err(|err| err.to_string())
+    }
+
+    fn column<'py>(table: &'py PyAny, name: &str) -> Option<&'py PyAny> {
+        table.get_item(name).ok()
+    }
+
+    fn time_stamps(py: Python, array: &PyAny) -> Result<Vec<u64>, String> {
+        let array = Self::buffer_object(array, "time_stamp")?;
+        if let Some(time_stamps) = Self::read(py, array, "time_stamp", |time_stamp: u64| Ok(time_stamp))? {
+            return Ok(time_stamps);
+        }
+        let time_stamps = Self::read(py, array, "time_stamp", |time_stamp: i64| {
+            u64::try_from(time_stamp).map_err(|_| format!("Negative time stamp {}", time_stamp))
+        })?;
+        time_stamps.ok_or_else(|| "Array 'time_stamp' has to hold 64 bit integers".to_string())
+    }
+
+    fn values(py: Python, array: &PyAny, name: &str) -> Result<Vec<f32>, String> {
+        let array = Self::buffer_object(array, name)?;
+        if let Some(values) = Self::read(py, array, name, |value: f32| Ok(value))? {
+            return Ok(values);
+        }
+        let values = Self::read(py, array, name, |value: f64| Ok(value as f32))?;
+        values.ok_or_else(|| format!("Array '{}' has to hold 32 or 64 bit floats", name))
+    }
+
+    fn buffer_object<'py>(array: &'py PyAny, name: &str) -> Result<&'py PyAny, String> {
+        if array.hasattr("to_numpy").unwrap_or(false) {
+            array.call_method0("to_numpy").map_err(|err| format!("Failed to convert '{}' to an array: {}", name, err))
+        } else {
+            Ok(array)
+        }
+    }
+
+    /// Reads a one-dimensional buffer into a column, converting every element on the way. Buffers that are not
+    /// contiguous are copied out first. `None` if the buffer holds another element type.
+    fn read<T: Element, U>(py: Python, array: &PyAny, name: &str, convert: impl Fn(T) -> Result<U, String>) -> Result<Option<Vec<U>>, String> {
+        let buffer = match PyBuffer::<T>::get(array) {
+            Ok(buffer) => buffer,
+            Err(_) => return Ok(None),
+        };
+        if buffer.dimensions() != 1 {
+            return Err(format!("Array '{}' has to be one-dimensional", name));
+        }
+        let values: Result<Vec<U>, String> = match buffer.as_slice(py) {
+            Some(cells) => cells.iter().map(|cell| convert(cell.get())).collect(),
+            None => buffer.to_vec(py)
+                .map_err(|err| format!("Failed to read array '{}': {}", name, err))?
+                .into_iter()
+                .map(convert)
+                .collect(),
+        };
+        values.map(Some)
+    }
+}
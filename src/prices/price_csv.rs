@@ -0,0 +1,158 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+use chrono::NaiveDateTime;
+
+use super::price_ohlc::PriceOhlc;
+use super::price_series::PriceKind;
+use super::price_tick::PriceTick;
+
+/// Prices parsed from a CSV file, all of the same kind.
+#[derive(Debug)]
+pub enum CsvPrices {
+    Tick(Vec<PriceTick>),
+    Ohlc(Vec<PriceOhlc>),
+}
+
+/// Reader for HistData price files, either plain `.csv` or zipped. Supported line formats:
+/// - MetaTrader bars: `2024.02.01,00:00,open,high,low,close,volume`
+/// - Generic ASCII bars: `20240201 000000;open;high;low;close;volume`
+/// - Generic ASCII ticks: `20240201 000000123,bid,ask,volume`
+///
+/// Time stamps in the files are local to `utc_offset_minutes` (HistData uses EST without DST, i.e. -300) and are converted to UTC.
+pub struct PriceCsv;
+
+impl PriceCsv {
+    pub fn load(path: &str, utc_offset_minutes: i32) -> Result<CsvPrices, String> {
+        let file = File::open(path).map_err(|err| format!("Failed to open '{}': {}", path, err))?;
+        let mut prices = None;
+        if path.to_lowercase().ends_with(".zip") {
+            let mut archive = zip::ZipArchive::new(file).map_err(|err| format!("Failed to open zip archive '{}': {}", path, err))?;
+            let mut names: Vec<String> = archive.file_names()
+                .filter(|name| name.to_lowercase().ends_with(".csv"))
+                .map(|name| name.to_string())
+                .collect();
+            if names.is_empty() {
+                return Err(format!("No csv file found in '{}'", path));
+            }
+            names.sort();
+            for name in names {
+                let entry = archive.by_name(&name).map_err(|err| format!("Failed to read '{}' from '{}': {}", name, path, err))?;
+                Self::read_lines(entry, &name, utc_offset_minutes, &mut prices)?;
+            }
+        } else {
+            Self::read_lines(file, path, utc_offset_minutes, &mut prices)?;
+        }
+
+        prices.ok_or(format!("No prices found in '{}'", path))
+    }
+
+    fn read_lines(reader: impl Read, name: &str, utc_offset_minutes: i32, prices: &mut Option<CsvPrices>) -> Result<(), String> {
+        let offset_ms = utc_offset_minutes as i64 * 60 * 1000;
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line.map_err(|err| format!("Failed to read '{}': {}", name, err))?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parse_error = |reason: &str| format!("Invalid line {} in '{}': {}", index + 1, name, reason);
+            let fields: Vec<&str> = line.split([',', ';']).map(|field| field.trim()).collect();
+            let (kind, time_stamp, values) = Self::parse_fields(&fields).map_err(parse_error)?;
+            let time_stamp = u64::try_from(time_stamp - offset_ms).map_err(|_| parse_error("time stamp before 1970"))?;
+
+            let prices = prices.get_or_insert_with(|| match kind {
+                PriceKind::Tick => CsvPrices::Tick(Vec::new()),
+                PriceKind::Ohlc => CsvPrices::Ohlc(Vec::new()),
+            });
+            match prices {
+                CsvPrices::Tick(prices) if kind == PriceKind::Tick => prices.push(PriceTick::new(time_stamp, values[0], values[1], None)),
+                CsvPrices::Ohlc(prices) if kind == PriceKind::Ohlc => prices.push(PriceOhlc::new(time_stamp, values[0], values[1], values[2], values[3])),
+                _ => return Err(parse_error("ticks and bars mixed in one file")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the price kind, the local time stamp in milliseconds and the price values of one line.
+    fn parse_fields(fields: &[&str]) -> Result<(PriceKind, i64, Vec<f32>), &'static str> {
+        let (kind, date_time, values) = if fields.len() >= 6 && fields[0].contains('.') {
+            let date_time = NaiveDateTime::parse_from_str(&format!("{} {}", fields[0], fields[1]), "%Y.%m.%d %H:%M")
+                .map_err(|_| "invalid MetaTrader date")?;
+            (PriceKind::Ohlc, date_time, &fields[2..6])
+        } else if fields.len() >= 5 && fields[0].len() == 15 {
+            let date_time = NaiveDateTime::parse_from_str(fields[0], "%Y%m%d %H%M%S")
+                .map_err(|_| "invalid bar date")?;
+            (PriceKind::Ohlc, date_time, &fields[1..5])
+        } else if fields.len() >= 3 && fields[0].len() == 18 {
+            let (date_time, millis) = fields[0].get(..15).zip(fields[0].get(15..)).ok_or("invalid tick date")?;
+            let millis: i64 = millis.parse().map_err(|_| "invalid tick milliseconds")?;
+            let date_time = NaiveDateTime::parse_from_str(date_time, "%Y%m%d %H%M%S")
+                .map_err(|_| "invalid tick date")?
+                + chrono::Duration::milliseconds(millis);
+            (PriceKind::Tick, date_time, &fields[1..3])
+        } else {
+            return Err("unknown format");
+        };
+        let values = values.iter()
+            .map(|value| value.parse::<f32>().map_err(|_| "invalid price"))
+            .collect::<Result<Vec<f32>, _>>()?;
+        Ok((kind, date_time.and_utc().timestamp_millis(), values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price::{Ohlc, Price, Tick};
+
+    fn read(content: &str, utc_offset_minutes: i32) -> Result<CsvPrices, String> {
+        let mut prices = None;
+        PriceCsv::read_lines(content.as_bytes(), "test.csv", utc_offset_minutes, &mut prices)?;
+        Ok(prices.expect("Prices read"))
+    }
+
+    #[test]
+    fn parses_metatrader_and_ascii_bars() {
+        let metatrader = read("2024.02.01,00:00,1.1,1.2,1.0,1.15,0\n", 0).unwrap();
+        let ascii = read("20240201 000000;1.1;1.2;1.0;1.15;0\n", 0).unwrap();
+        for prices in [metatrader, ascii] {
+            let bars = match prices {
+                CsvPrices::Ohlc(bars) => bars,
+                CsvPrices::Tick(_) => panic!("Expected bars"),
+            };
+            assert_eq!(bars[0].ts(), 1_706_745_600_000);
+            assert_eq!(bars[0].get(&(Tick::Bid, Ohlc::High)), 1.2);
+            assert_eq!(bars[0].get(&(Tick::Bid, Ohlc::Close)), 1.15);
+        }
+    }
+
+    #[test]
+    fn parses_ticks_with_milliseconds() {
+        let ticks = match read("20240201 000000123,1.1,1.2,0\n\n", 0).unwrap() {
+            CsvPrices::Tick(ticks) => ticks,
+            CsvPrices::Ohlc(_) => panic!("Expected ticks"),
+        };
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks[0].ts(), 1_706_745_600_123);
+        assert_eq!(ticks[0].get(&(Tick::Bid, Ohlc::Close)), 1.1);
+        assert_eq!(ticks[0].get(&(Tick::Ask, Ohlc::Close)), 1.2);
+    }
+
+    #[test]
+    fn converts_local_time_stamps_to_utc() {
+        let est = match read("2024.02.01,00:00,1.1,1.2,1.0,1.15,0\n", -300).unwrap() {
+            CsvPrices::Ohlc(bars) => bars,
+            CsvPrices::Tick(_) => panic!("Expected bars"),
+        };
+        assert_eq!(est[0].ts(), 1_706_745_600_000 + 5 * 60 * 60 * 1000);
+        assert!(read("1970.01.01,00:00,1.1,1.2,1.0,1.15,0\n", 60).unwrap_err().contains("before 1970"));
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(read("20240201 00000ä12,1.1,1.2,0\n", 0).unwrap_err().contains("line 1"));
+        assert!(read("2024.02.01,00:00,1.1,x,1.0,1.15,0\n", 0).unwrap_err().contains("invalid price"));
+        assert!(read("2024.02.01,00:00,1.1,1.2,1.0,1.15,0\n20240201 000000123,1.1,1.2,0\n", 0).unwrap_err().contains("mixed"));
+        assert!(read("hello\n", 0).unwrap_err().contains("unknown format"));
+    }
+}
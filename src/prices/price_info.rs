@@ -0,0 +1,25 @@
+use dict_derive::IntoPyObject;
+
+use super::price_series::PriceSeries;
+
+#[derive(Debug, Clone, IntoPyObject)]
+pub struct PriceInfo {
+    pub symbol: String,
+    pub kind: String,
+    pub count: usize,
+    pub time_stamp_first: Option<u64>,
+    pub time_stamp_last: Option<u64>,
+}
+
+impl PriceInfo {
+    pub fn new(symbol: &str, series: &PriceSeries) -> Self {
+        let time_stamps = series.as_slice().time_stamps();
+        PriceInfo {
+            symbol: symbol.to_string(),
+            kind: series.kind().name().to_string(),
+            count: time_stamps.len(),
+            time_stamp_first: time_stamps.first().copied(),
+            time_stamp_last: time_stamps.last().copied(),
+        }
+    }
+}
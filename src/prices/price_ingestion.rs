@@ -0,0 +1,40 @@
+use dict_derive::IntoPyObject;
+
+/// How prices with a time stamp that is already present are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestionPolicy {
+    Reject,
+    KeepFirst,
+    KeepLast,
+}
+
+impl IngestionPolicy {
+    pub fn parse(policy: &str) -> Result<Self, String> {
+        match policy {
+            "reject" => Ok(IngestionPolicy::Reject),
+            "keep_first" => Ok(IngestionPolicy::KeepFirst),
+            "keep_last" => Ok(IngestionPolicy::KeepLast),
+            _ => Err(format!("Invalid ingestion policy '{}', expected 'reject', 'keep_first' or 'keep_last'", policy)),
+        }
+    }
+}
+
+/// Summary of what was fixed while adding the prices of one symbol.
+#[derive(Debug, Clone, IntoPyObject)]
+pub struct IngestionReport {
+    pub added: usize,
+    pub reordered: usize,
+    pub duplicates: usize,
+    pub overlapping: usize,
+}
+
+impl IngestionReport {
+    pub fn new() -> Self {
+        IngestionReport {
+            added: 0,
+            reordered: 0,
+            duplicates: 0,
+            overlapping: 0,
+        }
+    }
+}
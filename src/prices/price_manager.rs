@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 
 use super::price::Price;
+use super::price_info::PriceInfo;
+use super::price_ingestion::{IngestionPolicy, IngestionReport};
+use super::price_series::{PriceKind, PriceSeries};
 
 pub struct PriceManager {
-    pub prices: HashMap<String, Vec<Box<dyn Price>>>,
+    pub prices: HashMap<String, PriceSeries>,
 }
 
 impl PriceManager {
@@ -13,9 +16,277 @@ impl PriceManager {
         }
     }
 
-    pub fn add_prices(&mut self, prices: HashMap<String, Vec<Box<dyn Price>>>) {
-        for (key, value) in prices {
-            self.prices.entry(key).or_insert_with(Vec::new).extend(value);
+    /// Adds prices in time stamp order. Batches are sorted, duplicated time stamps are resolved with `policy` and
+    /// batches overlapping the stored prices are merged. Nothing is stored if any symbol fails.
+    pub fn add_prices<P: Price>(&mut self, kind: PriceKind, prices: HashMap<String, Vec<P>>, policy: IngestionPolicy) -> Result<HashMap<String, IngestionReport>, String> {
+        let mut prepared = Vec::new();
+        for (symbol, batch) in prices {
+            let existing = self.prices.get(&symbol);
+            if existing.is_some_and(|series| series.kind() != kind) {
+                return Err(format!("Price kind does not match the prices already stored for symbol '{}'", symbol));
+            }
+            let (batch, report) = Self::prepare_batch(&symbol, batch, existing, policy)?;
+            prepared.push((symbol, batch, report));
         }
+
+        let mut reports = HashMap::new();
+        for (symbol, batch, mut report) in prepared {
+            let series = self.prices.entry(symbol.clone()).or_insert_with(|| PriceSeries::new(kind));
+            let len_before = series.as_slice().len();
+            if report.overlapping == 0 {
+                series.reserve(batch.len());
+                for price in batch.iter() {
+                    series.push(price);
+                }
+            } else {
+                *series = Self::merge(kind, series, &batch);
+            }
+            report.added = series.as_slice().len() - len_before;
+            reports.insert(symbol, report);
+        }
+        Ok(reports)
+    }
+
+    /// Adds a whole series for `symbol`, handled like a batch passed to `add_prices`. A sorted series of a symbol that is
+    /// not stored yet is kept as it is instead of being copied.
+    pub fn add_series(&mut self, symbol: String, series: PriceSeries, policy: IngestionPolicy) -> Result<IngestionReport, String> {
+        let prices = series.as_slice();
+        if !self.prices.contains_key(&symbol) && prices.time_stamps().windows(2).all(|pair| pair[0] < pair[1]) {
+            let mut report = IngestionReport::new();
+            report.added = prices.len();
+            self.prices.insert(symbol, series);
+            return Ok(report);
+        }
+        let batch: Vec<_> = (0..prices.len()).map(|index| prices.at(index)).collect();
+        let mut reports = self.add_prices(series.kind(), HashMap::from([(symbol.clone(), batch)]), policy)?;
+        Ok(reports.remove(&symbol).expect("Report for added symbol"))
+    }
+
+    /// Stores the series of every symbol in `prices`, replacing what is stored for it. Other symbols are kept.
+    pub fn replace_prices(&mut self, prices: HashMap<String, PriceSeries>) {
+        self.prices.extend(prices);
     }
-}
\ No newline at end of file
+
+    pub fn list(&self) -> Vec<PriceInfo> {
+        let mut infos: Vec<PriceInfo> = self.prices.iter().map(|(symbol, series)| PriceInfo::new(symbol, series)).collect();
+        infos.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        infos
+    }
+
+    pub fn remove(&mut self, symbol: &str) -> bool {
+        self.prices.remove(symbol).is_some()
+    }
+
+    /// Keeps only the prices of `symbol` inside the time range, a symbol without prices left is removed.
+    pub fn trim(&mut self, symbol: &str, time_stamp_from: Option<u64>, time_stamp_to: Option<u64>) -> Result<usize, String> {
+        let series = self.prices.get_mut(symbol).ok_or(format!("No prices stored for symbol '{}'", symbol))?;
+        let removed = series.trim(time_stamp_from, time_stamp_to);
+        if series.is_empty() {
+            self.prices.remove(symbol);
+        }
+        Ok(removed)
+    }
+
+    pub fn clear(&mut self) {
+        self.prices.clear();
+        self.prices.shrink_to_fit();
+    }
+
+    /// Sorts the batch and drops duplicates that `policy` does not keep. With `KeepLast` duplicates of stored prices remain
+    /// in the batch and replace the stored price while merging.
+    fn prepare_batch<P: Price>(symbol: &str, mut batch: Vec<P>, existing: Option<&PriceSeries>, policy: IngestionPolicy) -> Result<(Vec<P>, IngestionReport), String> {
+        let mut report = IngestionReport::new();
+        report.reordered = batch.windows(2).filter(|pair| pair[1].ts() < pair[0].ts()).count();
+        if report.reordered > 0 {
+            batch.sort_by_key(|price| price.ts());
+        }
+
+        let len_before = batch.len();
+        match policy {
+            IngestionPolicy::Reject => {
+                if let Some(pair) = batch.windows(2).find(|pair| pair[0].ts() == pair[1].ts()) {
+                    return Err(format!("Duplicate time stamp {} for symbol '{}'", pair[0].ts(), symbol));
+                }
+            }
+            IngestionPolicy::KeepFirst => batch.dedup_by_key(|price| price.ts()),
+            IngestionPolicy::KeepLast => {
+                batch.reverse();
+                batch.dedup_by_key(|price| price.ts());
+                batch.reverse();
+            }
+        }
+        report.duplicates = len_before - batch.len();
+
+        let time_stamps = match existing {
+            Some(series) => series.as_slice().time_stamps(),
+            None => return Ok((batch, report)),
+        };
+        let last_time_stamp = match time_stamps.last() {
+            Some(&time_stamp) => time_stamp,
+            None => return Ok((batch, report)),
+        };
+        let mut duplicate_error = None;
+        batch.retain(|price| {
+            if price.ts() > last_time_stamp {
+                return true;
+            }
+            report.overlapping += 1;
+            if time_stamps.binary_search(&price.ts()).is_err() {
+                return true;
+            }
+            report.duplicates += 1;
+            match policy {
+                IngestionPolicy::Reject => {
+                    duplicate_error.get_or_insert(price.ts());
+                    true
+                }
+                IngestionPolicy::KeepFirst => false,
+                IngestionPolicy::KeepLast => true,
+            }
+        });
+        if let Some(time_stamp) = duplicate_error {
+            return Err(format!("Time stamp {} for symbol '{}' is already stored", time_stamp, symbol));
+        }
+        Ok((batch, report))
+    }
+
+    fn merge<P: Price>(kind: PriceKind, series: &PriceSeries, batch: &[P]) -> PriceSeries {
+        let existing = series.as_slice();
+        let mut merged = PriceSeries::new(kind);
+        merged.reserve(existing.len() + batch.len());
+        let mut index = 0;
+        for price in batch {
+            while index < existing.len() && existing.ts(index) < price.ts() {
+                merged.push(&existing.at(index));
+                index += 1;
+            }
+            if index < existing.len() && existing.ts(index) == price.ts() {
+                index += 1;
+            }
+            merged.push(price);
+        }
+        while index < existing.len() {
+            merged.push(&existing.at(index));
+            index += 1;
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price::{Ohlc, Tick};
+    use crate::prices::price_tick::PriceTick;
+
+    fn ticks(time_stamps: &[u64]) -> Vec<PriceTick> {
+        time_stamps.iter().map(|&time_stamp| PriceTick::new(time_stamp, 1.0 + time_stamp as f32, 1.5 + time_stamp as f32, None)).collect()
+    }
+
+    fn series(time_stamps: &[u64]) -> PriceSeries {
+        let mut series = PriceSeries::new(PriceKind::Tick);
+        ticks(time_stamps).iter().for_each(|price| series.push(price));
+        series
+    }
+
+    #[test]
+    fn replace_prices_replaces_given_symbols_and_keeps_others() {
+        let mut price_manager = PriceManager::new();
+        price_manager.replace_prices(HashMap::from([("A".to_string(), series(&[0, 1])), ("B".to_string(), series(&[0]))]));
+        price_manager.replace_prices(HashMap::from([("A".to_string(), series(&[5]))]));
+        assert_eq!(price_manager.prices["A"].as_slice().time_stamps(), &[5]);
+        assert_eq!(price_manager.prices["B"].as_slice().time_stamps(), &[0]);
+    }
+
+    fn add(price_manager: &mut PriceManager, time_stamps: &[u64], policy: IngestionPolicy) -> Result<IngestionReport, String> {
+        let mut reports = price_manager.add_prices(PriceKind::Tick, HashMap::from([("A".to_string(), ticks(time_stamps))]), policy)?;
+        Ok(reports.remove("A").unwrap())
+    }
+
+    #[test]
+    fn batches_are_sorted_and_deduplicated() {
+        let mut price_manager = PriceManager::new();
+        let report = add(&mut price_manager, &[3, 1, 2, 2], IngestionPolicy::KeepFirst).unwrap();
+        assert_eq!((report.added, report.reordered, report.duplicates), (3, 1, 1));
+        assert_eq!(price_manager.prices["A"].as_slice().time_stamps(), &[1, 2, 3]);
+        assert!(add(&mut PriceManager::new(), &[1, 1], IngestionPolicy::Reject).unwrap_err().contains("Duplicate time stamp 1"));
+    }
+
+    #[test]
+    fn overlapping_batches_are_merged_by_policy() {
+        let mut price_manager = PriceManager::new();
+        add(&mut price_manager, &[0, 10, 20], IngestionPolicy::Reject).unwrap();
+
+        let report = add(&mut price_manager, &[5, 10, 30], IngestionPolicy::KeepFirst).unwrap();
+        assert_eq!((report.added, report.overlapping, report.duplicates), (2, 2, 1));
+        let prices = price_manager.prices["A"].as_slice();
+        assert_eq!(prices.time_stamps(), &[0, 5, 10, 20, 30]);
+
+        let mut updated = ticks(&[10]);
+        updated[0] = PriceTick::new(10, 9.0, 9.5, None);
+        price_manager.add_prices(PriceKind::Tick, HashMap::from([("A".to_string(), updated)]), IngestionPolicy::KeepLast).unwrap();
+        let prices = price_manager.prices["A"].as_slice();
+        assert_eq!(prices.len(), 5);
+        assert_eq!(prices.get(2, &(Tick::Bid, Ohlc::Close)), 9.0);
+
+        assert!(add(&mut price_manager, &[20], IngestionPolicy::Reject).unwrap_err().contains("already stored"));
+        assert_eq!(price_manager.prices["A"].as_slice().len(), 5);
+    }
+
+    #[test]
+    fn series_are_added_like_batches() {
+        let mut price_manager = PriceManager::new();
+        let report = price_manager.add_series("A".to_string(), series(&[0, 10]), IngestionPolicy::Reject).unwrap();
+        assert_eq!((report.added, report.reordered), (2, 0));
+        let report = price_manager.add_series("B".to_string(), series(&[10, 0, 0]), IngestionPolicy::KeepFirst).unwrap();
+        assert_eq!((report.added, report.reordered, report.duplicates), (2, 1, 1));
+        assert_eq!(price_manager.prices["B"].as_slice().time_stamps(), &[0, 10]);
+
+        let report = price_manager.add_series("A".to_string(), series(&[5, 20]), IngestionPolicy::Reject).unwrap();
+        assert_eq!((report.added, report.overlapping), (2, 1));
+        let prices = price_manager.prices["A"].as_slice();
+        assert_eq!(prices.time_stamps(), &[0, 5, 10, 20]);
+        assert_eq!(prices.column(&(Tick::Ask, Ohlc::Close)), &[1.5, 6.5, 11.5, 21.5]);
+    }
+
+    #[test]
+    fn nothing_is_stored_if_a_symbol_fails() {
+        let mut price_manager = PriceManager::new();
+        let prices = HashMap::from([("A".to_string(), ticks(&[1, 2])), ("B".to_string(), ticks(&[1, 1]))]);
+        assert!(price_manager.add_prices(PriceKind::Tick, prices, IngestionPolicy::Reject).is_err());
+        assert!(price_manager.prices.is_empty());
+    }
+
+    #[test]
+    fn lists_symbols_in_order() {
+        let mut price_manager = PriceManager::new();
+        price_manager.replace_prices(HashMap::from([("B".to_string(), series(&[5, 7])), ("A".to_string(), series(&[1]))]));
+        let infos = price_manager.list();
+        assert_eq!(infos.iter().map(|info| info.symbol.as_str()).collect::<Vec<_>>(), vec!["A", "B"]);
+        assert_eq!((infos[1].kind.as_str(), infos[1].count, infos[1].time_stamp_first, infos[1].time_stamp_last), ("tick", 2, Some(5), Some(7)));
+    }
+
+    #[test]
+    fn trim_keeps_the_range_and_removes_emptied_symbols() {
+        let mut price_manager = PriceManager::new();
+        price_manager.replace_prices(HashMap::from([("A".to_string(), series(&[0, 10, 20, 30]))]));
+        assert_eq!(price_manager.trim("A", Some(5), Some(20)), Ok(2));
+        let prices = price_manager.prices["A"].as_slice();
+        assert_eq!(prices.time_stamps(), &[10, 20]);
+        assert_eq!(prices.column(&(Tick::Bid, Ohlc::Close)), &[11.0, 21.0]);
+
+        assert_eq!(price_manager.trim("A", Some(100), None), Ok(2));
+        assert!(!price_manager.prices.contains_key("A"));
+        assert!(price_manager.trim("A", None, None).is_err());
+    }
+
+    #[test]
+    fn remove_and_clear_drop_symbols() {
+        let mut price_manager = PriceManager::new();
+        price_manager.replace_prices(HashMap::from([("A".to_string(), series(&[0])), ("B".to_string(), series(&[0]))]));
+        assert!(price_manager.remove("A"));
+        assert!(!price_manager.remove("A"));
+        price_manager.clear();
+        assert!(price_manager.list().is_empty());
+    }
+}
@@ -1,7 +1,10 @@
 use dict_derive::{FromPyObject, IntoPyObject};
 
-use super::price::{Ohlc, Price, PriceType};
+use super::price::{Ohlc, Price, PriceType, Tick};
+use super::spread::Spread;
 
+/// A bar with bid prices in `open`, `high`, `low` and `close`. The optional `ask_*` fields carry the ask side,
+/// bars without them have ask equal to bid unless a spread is configured in the backtest conditions.
 #[derive(Clone, Debug, FromPyObject, IntoPyObject)]
 pub struct PriceOhlc {
     time_stamp: u64,
@@ -9,19 +12,90 @@ pub struct PriceOhlc {
     high: f32,
     low: f32,
     close: f32,
+    ask_open: Option<f32>,
+    ask_high: Option<f32>,
+    ask_low: Option<f32>,
+    ask_close: Option<f32>,
+}
+
+impl PriceOhlc {
+    pub fn new(time_stamp: u64, open: f32, high: f32, low: f32, close: f32) -> Self {
+        PriceOhlc {
+            time_stamp,
+            open,
+            high,
+            low,
+            close,
+            ask_open: None,
+            ask_high: None,
+            ask_low: None,
+            ask_close: None,
+        }
+    }
+
+    /// Ask prices have to be given for all of open, high, low and close or for none of them.
+    pub fn validate(&self) -> Result<(), String> {
+        let given = [self.ask_open, self.ask_high, self.ask_low, self.ask_close].iter().filter(|ask| ask.is_some()).count();
+        if given != 0 && given != 4 {
+            return Err(format!("Bar at {} has to carry ask_open, ask_high, ask_low and ask_close or none of them", self.time_stamp));
+        }
+        Ok(())
+    }
+
+    /// Derives the ask side from `spread` for a bar that carries no ask prices itself.
+    pub fn apply_spread(&mut self, spread: &Spread) {
+        if self.ask_open.is_some() {
+            return;
+        }
+        let offset = spread.at(self.time_stamp);
+        self.ask_open = Some(self.open + offset);
+        self.ask_high = Some(self.high + offset);
+        self.ask_low = Some(self.low + offset);
+        self.ask_close = Some(self.close + offset);
+    }
 }
 
 impl Price for PriceOhlc {
     fn get(&self, price_type: &PriceType) -> f32 {
-        match price_type.1 {
-            Ohlc::Open => self.open,
-            Ohlc::High => self.high,
-            Ohlc::Low => self.low,
-            Ohlc::Close => self.close,
+        match price_type {
+            (Tick::Bid, Ohlc::Open) => self.open,
+            (Tick::Bid, Ohlc::High) => self.high,
+            (Tick::Bid, Ohlc::Low) => self.low,
+            (Tick::Bid, Ohlc::Close) => self.close,
+            (Tick::Ask, Ohlc::Open) => self.ask_open.unwrap_or(self.open),
+            (Tick::Ask, Ohlc::High) => self.ask_high.unwrap_or(self.high),
+            (Tick::Ask, Ohlc::Low) => self.ask_low.unwrap_or(self.low),
+            (Tick::Ask, Ohlc::Close) => self.ask_close.unwrap_or(self.close),
         }
     }
 
     fn ts(&self) -> u64 {
         self.time_stamp
     }
-}
\ No newline at end of file
+
+    fn has_ask(&self) -> bool {
+        self.ask_open.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ask_prices_are_given_for_all_or_none_of_the_bar() {
+        let mut bar = PriceOhlc::new(0, 1.0, 1.2, 0.9, 1.1);
+        assert!(bar.validate().is_ok() && !bar.has_ask());
+        bar.ask_open = Some(1.05);
+        bar.ask_close = Some(1.15);
+        assert!(bar.validate().is_err());
+
+        // Ask prices equal to the bid are a spread of zero, not missing ask prices
+        bar.apply_spread(&Spread { fixed: Some(0.0), hourly: None });
+        assert!(bar.validate().is_err());
+        let mut bar = PriceOhlc::new(0, 1.0, 1.2, 0.9, 1.1);
+        bar.apply_spread(&Spread { fixed: Some(0.0), hourly: None });
+        assert!(bar.validate().is_ok() && bar.has_ask());
+        assert_eq!(bar.get(&(Tick::Ask, Ohlc::High)), 1.2);
+    }
+}
@@ -0,0 +1,127 @@
+use super::price::Auxiliary;
+use super::price_series::{PriceKind, PriceSeries};
+use super::price_slice::{PriceColumns, PriceSlice};
+
+/// Aggregates ticks or bars into bars of a coarser timeframe. Bars are labeled with the start of their period.
+pub struct PriceResampler;
+
+impl PriceResampler {
+    /// Parses timeframes like "30s", "1m", "5m", "1h" or "1d" into milliseconds.
+    pub fn parse_timeframe(timeframe: &str) -> Result<u64, String> {
+        let timeframe = timeframe.trim();
+        let split = timeframe.find(|c: char| !c.is_ascii_digit()).unwrap_or(timeframe.len());
+        let (amount, unit) = timeframe.split_at(split);
+        let amount: u64 = amount.parse().map_err(|_| format!("Invalid timeframe '{}'", timeframe))?;
+        let unit_ms = match unit {
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            _ => return Err(format!("Invalid timeframe unit in '{}', expected s, m, h or d", timeframe)),
+        };
+        if amount == 0 {
+            return Err(format!("Timeframe '{}' must be greater than zero", timeframe));
+        }
+        Ok(amount * unit_ms)
+    }
+
+    pub fn resample(prices: &PriceSlice, timeframe_ms: u64) -> PriceSeries {
+        let periods = Self::group_periods(prices.time_stamps(), timeframe_ms);
+        let time_stamps = periods.iter().map(|&(start, _)| prices.ts(start) / timeframe_ms * timeframe_ms).collect();
+
+        let columns = match prices.columns() {
+            PriceColumns::Tick { bid, ask } => {
+                let mut columns = Self::aggregate(&periods, [bid, bid, bid, bid]);
+                columns.extend(Self::aggregate(&periods, [ask, ask, ask, ask]));
+                columns
+            }
+            PriceColumns::Ohlc { bid, ask } => {
+                let mut columns = Self::aggregate(&periods, [bid.open, bid.high, bid.low, bid.close]);
+                if let Some(ask) = ask {
+                    columns.extend(Self::aggregate(&periods, [ask.open, ask.high, ask.low, ask.close]));
+                }
+                columns
+            }
+        };
+
+        let sentiment = prices.auxiliary(&Auxiliary::Sentiment).map(|sentiment| {
+            periods.iter()
+                .map(|&(start, end)| sentiment[start..end].iter().rev().find(|value| !value.is_nan()).copied().unwrap_or(f32::NAN))
+                .collect()
+        });
+
+        PriceSeries::from_columns(PriceKind::Ohlc, time_stamps, columns, sentiment).expect("Resampled columns are consistent")
+    }
+
+    /// Index ranges of the prices falling into the same period.
+    fn group_periods(time_stamps: &[u64], timeframe_ms: u64) -> Vec<(usize, usize)> {
+        let mut periods = Vec::new();
+        let mut start = 0;
+        for index in 1..=time_stamps.len() {
+            if index == time_stamps.len() || time_stamps[index] / timeframe_ms != time_stamps[start] / timeframe_ms {
+                periods.push((start, index));
+                start = index;
+            }
+        }
+        periods
+    }
+
+    fn aggregate(periods: &[(usize, usize)], [open, high, low, close]: [&[f32]; 4]) -> Vec<Vec<f32>> {
+        vec![
+            periods.iter().map(|&(start, _)| open[start]).collect(),
+            periods.iter().map(|&(start, end)| high[start..end].iter().copied().fold(f32::NAN, f32::max)).collect(),
+            periods.iter().map(|&(start, end)| low[start..end].iter().copied().fold(f32::NAN, f32::min)).collect(),
+            periods.iter().map(|&(_, end)| close[end - 1]).collect(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price::{Ohlc, Tick};
+    use crate::prices::price_tick::PriceTick;
+
+    #[test]
+    fn parses_timeframes() {
+        assert_eq!(PriceResampler::parse_timeframe("30s"), Ok(30_000));
+        assert_eq!(PriceResampler::parse_timeframe(" 5m "), Ok(300_000));
+        assert_eq!(PriceResampler::parse_timeframe("1d"), Ok(86_400_000));
+        assert!(PriceResampler::parse_timeframe("0h").is_err());
+        assert!(PriceResampler::parse_timeframe("5w").is_err());
+        assert!(PriceResampler::parse_timeframe("m").is_err());
+    }
+
+    #[test]
+    fn ticks_become_bid_and_ask_bars_labeled_with_their_period() {
+        let mut ticks = PriceSeries::new(PriceKind::Tick);
+        for (time_stamp, bid, sentiment) in [(61_000, 1.2, Some(0.3)), (62_000, 1.5, None), (65_000, 1.1, None), (121_000, 1.3, Some(0.6))] {
+            ticks.push(&PriceTick::new(time_stamp, bid, bid + 0.1, sentiment));
+        }
+        let bars = PriceResampler::resample(&ticks.as_slice(), 60_000);
+        let bars = bars.as_slice();
+        assert_eq!(bars.time_stamps(), &[60_000, 120_000]);
+        assert_eq!(bars.column(&(Tick::Bid, Ohlc::Open)), &[1.2, 1.3]);
+        assert_eq!(bars.column(&(Tick::Bid, Ohlc::High)), &[1.5, 1.3]);
+        assert_eq!(bars.column(&(Tick::Bid, Ohlc::Low)), &[1.1, 1.3]);
+        assert_eq!(bars.column(&(Tick::Bid, Ohlc::Close)), &[1.1, 1.3]);
+        assert_eq!(bars.column(&(Tick::Ask, Ohlc::High)), &[1.6, 1.4]);
+        assert_eq!(bars.get_auxiliary(0, &Auxiliary::Sentiment), Some(0.3));
+        assert_eq!(bars.get_auxiliary(1, &Auxiliary::Sentiment), Some(0.6));
+    }
+
+    #[test]
+    fn bars_become_coarser_bars() {
+        let time_stamps = vec![0, 60_000, 120_000, 180_000];
+        let columns = vec![vec![1.0, 2.0, 3.0, 4.0], vec![1.5, 2.5, 3.5, 4.5], vec![0.5, 1.5, 2.5, 3.5], vec![1.2, 2.2, 3.2, 4.2]];
+        let bars = PriceSeries::from_columns(PriceKind::Ohlc, time_stamps, columns, None).unwrap();
+        let coarse = PriceResampler::resample(&bars.as_slice(), 120_000);
+        let coarse = coarse.as_slice();
+        assert_eq!(coarse.time_stamps(), &[0, 120_000]);
+        assert_eq!(coarse.column(&(Tick::Bid, Ohlc::Open)), &[1.0, 3.0]);
+        assert_eq!(coarse.column(&(Tick::Bid, Ohlc::High)), &[2.5, 4.5]);
+        assert_eq!(coarse.column(&(Tick::Bid, Ohlc::Low)), &[0.5, 2.5]);
+        assert_eq!(coarse.column(&(Tick::Bid, Ohlc::Close)), &[2.2, 4.2]);
+        assert!(PriceResampler::resample(&bars.as_slice().slice(0..0), 60_000).is_empty());
+    }
+}
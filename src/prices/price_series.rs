@@ -0,0 +1,370 @@
+use std::sync::{Arc, Mutex};
+
+use super::price::{Auxiliary, Ohlc, Price, Tick};
+use super::price_slice::{OhlcSlice, PriceColumns, PriceSlice};
+use super::spread::Spread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceKind {
+    Tick,
+    Ohlc,
+}
+
+impl PriceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceKind::Tick => "tick",
+            PriceKind::Ohlc => "ohlc",
+        }
+    }
+}
+
+fn retain_range<T>(column: &mut Vec<T>, start: usize, end: usize) {
+    column.truncate(end);
+    column.drain(..start);
+    column.shrink_to_fit();
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OhlcColumns {
+    open: Vec<f32>,
+    high: Vec<f32>,
+    low: Vec<f32>,
+    close: Vec<f32>,
+}
+
+impl OhlcColumns {
+    fn from_columns(columns: &mut impl Iterator<Item = Vec<f32>>) -> Self {
+        OhlcColumns {
+            open: columns.next().unwrap(),
+            high: columns.next().unwrap(),
+            low: columns.next().unwrap(),
+            close: columns.next().unwrap(),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.open.reserve(additional);
+        self.high.reserve(additional);
+        self.low.reserve(additional);
+        self.close.reserve(additional);
+    }
+
+    fn retain_range(&mut self, start: usize, end: usize) {
+        retain_range(&mut self.open, start, end);
+        retain_range(&mut self.high, start, end);
+        retain_range(&mut self.low, start, end);
+        retain_range(&mut self.close, start, end);
+    }
+
+    /// Ask columns with every bar shifted by `offset`.
+    fn with_spread(&self, offset: impl Fn(usize) -> f32) -> OhlcColumns {
+        let shift = |bid: &[f32]| bid.iter().enumerate().map(|(index, bid)| bid + offset(index)).collect();
+        OhlcColumns {
+            open: shift(&self.open),
+            high: shift(&self.high),
+            low: shift(&self.low),
+            close: shift(&self.close),
+        }
+    }
+
+    fn push(&mut self, price: &impl Price, tick: Tick) {
+        self.open.push(price.get(&(tick.clone(), Ohlc::Open)));
+        self.high.push(price.get(&(tick.clone(), Ohlc::High)));
+        self.low.push(price.get(&(tick.clone(), Ohlc::Low)));
+        self.close.push(price.get(&(tick, Ohlc::Close)));
+    }
+
+    pub fn as_slice(&self) -> OhlcSlice<'_> {
+        OhlcSlice {
+            open: &self.open,
+            high: &self.high,
+            low: &self.low,
+            close: &self.close,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PriceData {
+    Tick {
+        bid: Vec<f32>,
+        ask: Vec<f32>,
+    },
+    Ohlc {
+        bid: OhlcColumns,
+        ask: Option<OhlcColumns>,
+    },
+}
+
+/// Ask columns derived from a spread, kept until the prices change so that backtests with the same spread share them.
+#[derive(Debug, Default)]
+struct SpreadAskCache(Mutex<Option<(Spread, Option<Arc<OhlcColumns>>)>>);
+
+impl Clone for SpreadAskCache {
+    fn clone(&self) -> Self {
+        SpreadAskCache::default()
+    }
+}
+
+/// Columnar storage for the prices of one symbol. All columns have the same length as `time_stamps`.
+/// Bars only carry separate ask columns if they were built with them, otherwise ask equals bid.
+/// Auxiliary columns are only allocated once the first price carrying them arrives, missing values are stored as NaN.
+#[derive(Debug, Clone)]
+pub struct PriceSeries {
+    time_stamps: Vec<u64>,
+    data: PriceData,
+    sentiment: Option<Vec<f32>>,
+    spread_ask: SpreadAskCache,
+}
+
+impl PriceSeries {
+    pub fn new(kind: PriceKind) -> Self {
+        let data = match kind {
+            PriceKind::Tick => PriceData::Tick {
+                bid: Vec::new(),
+                ask: Vec::new(),
+            },
+            PriceKind::Ohlc => PriceData::Ohlc {
+                bid: OhlcColumns::default(),
+                ask: None,
+            },
+        };
+        PriceSeries {
+            time_stamps: Vec::new(),
+            data,
+            sentiment: None,
+            spread_ask: SpreadAskCache::default(),
+        }
+    }
+
+    /// Builds a series from raw columns, `columns` holds bid and ask for ticks or open, high, low and close for bars,
+    /// optionally followed by open, high, low and close of the ask side.
+    pub fn from_columns(kind: PriceKind, time_stamps: Vec<u64>, columns: Vec<Vec<f32>>, sentiment: Option<Vec<f32>>) -> Result<Self, &'static str> {
+        let valid_column_count = match kind {
+            PriceKind::Tick => columns.len() == 2,
+            PriceKind::Ohlc => columns.len() == 4 || columns.len() == 8,
+        };
+        if !valid_column_count {
+            return Err("Invalid number of price columns");
+        }
+        if columns.iter().chain(sentiment.iter()).any(|column| column.len() != time_stamps.len()) {
+            return Err("Price columns differ in length");
+        }
+        let with_ask = columns.len() == 8;
+        let mut columns = columns.into_iter();
+        let data = match kind {
+            PriceKind::Tick => PriceData::Tick {
+                bid: columns.next().unwrap(),
+                ask: columns.next().unwrap(),
+            },
+            PriceKind::Ohlc => PriceData::Ohlc {
+                bid: OhlcColumns::from_columns(&mut columns),
+                ask: with_ask.then(|| OhlcColumns::from_columns(&mut columns)),
+            },
+        };
+        Ok(PriceSeries {
+            time_stamps,
+            data,
+            sentiment,
+            spread_ask: SpreadAskCache::default(),
+        })
+    }
+
+    pub fn kind(&self) -> PriceKind {
+        match self.data {
+            PriceData::Tick { .. } => PriceKind::Tick,
+            PriceData::Ohlc { .. } => PriceKind::Ohlc,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time_stamps.is_empty()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.time_stamps.reserve(additional);
+        match &mut self.data {
+            PriceData::Tick { bid, ask } => {
+                bid.reserve(additional);
+                ask.reserve(additional);
+            }
+            PriceData::Ohlc { bid, ask } => {
+                bid.reserve(additional);
+                if let Some(ask) = ask {
+                    ask.reserve(additional);
+                }
+            }
+        }
+    }
+
+    pub fn push(&mut self, price: &impl Price) {
+        self.clear_spread_ask();
+        let sentiment = price.get_auxiliary(&Auxiliary::Sentiment);
+        if sentiment.is_some() && self.sentiment.is_none() {
+            self.sentiment = Some(vec![f32::NAN; self.time_stamps.len()]);
+        }
+        if let Some(column) = &mut self.sentiment {
+            column.push(sentiment.unwrap_or(f32::NAN));
+        }
+
+        self.time_stamps.push(price.ts());
+        match &mut self.data {
+            PriceData::Tick { bid, ask } => {
+                bid.push(price.get(&(Tick::Bid, Ohlc::Close)));
+                ask.push(price.get(&(Tick::Ask, Ohlc::Close)));
+            }
+            PriceData::Ohlc { bid, ask } => {
+                if price.has_ask() && ask.is_none() {
+                    *ask = Some(bid.clone());
+                }
+                bid.push(price, Tick::Bid);
+                if let Some(ask) = ask {
+                    ask.push(price, Tick::Ask);
+                }
+            }
+        }
+    }
+
+    /// Drops all prices outside of `time_stamp_from..=time_stamp_to` and releases their memory. Returns the number of removed prices.
+    pub fn trim(&mut self, time_stamp_from: Option<u64>, time_stamp_to: Option<u64>) -> usize {
+        self.clear_spread_ask();
+        let len_before = self.time_stamps.len();
+        let start = time_stamp_from.map_or(0, |from| self.time_stamps.partition_point(|&time_stamp| time_stamp < from));
+        let end = time_stamp_to.map_or(len_before, |to| self.time_stamps.partition_point(|&time_stamp| time_stamp <= to)).max(start);
+
+        retain_range(&mut self.time_stamps, start, end);
+        match &mut self.data {
+            PriceData::Tick { bid, ask } => {
+                retain_range(bid, start, end);
+                retain_range(ask, start, end);
+            }
+            PriceData::Ohlc { bid, ask } => {
+                bid.retain_range(start, end);
+                if let Some(ask) = ask {
+                    ask.retain_range(start, end);
+                }
+            }
+        }
+        if let Some(sentiment) = &mut self.sentiment {
+            retain_range(sentiment, start, end);
+        }
+        len_before - self.time_stamps.len()
+    }
+
+    /// Ask columns with the bid side shifted by `spread` for bars stored without any ask prices. `None` for ticks and
+    /// for bars with an ask column, even where a bar of it was given without ask prices. The columns are cached per spread.
+    pub fn spread_ask(&self, spread: &Spread) -> Option<Arc<OhlcColumns>> {
+        let bid = match &self.data {
+            PriceData::Ohlc { bid, ask: None } => bid,
+            _ => return None,
+        };
+        let mut cache = self.spread_ask.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((spread_cached, columns)) = cache.as_ref() {
+            if spread_cached == spread {
+                return columns.clone();
+            }
+        }
+
+        let columns = Some(Arc::new(bid.with_spread(|index| spread.at(self.time_stamps[index]))));
+        *cache = Some((spread.clone(), columns.clone()));
+        columns
+    }
+
+    fn clear_spread_ask(&mut self) {
+        *self.spread_ask.0.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+
+    pub fn as_slice(&self) -> PriceSlice<'_> {
+        let columns = match &self.data {
+            PriceData::Tick { bid, ask } => PriceColumns::Tick { bid, ask },
+            PriceData::Ohlc { bid, ask } => PriceColumns::Ohlc {
+                bid: bid.as_slice(),
+                ask: ask.as_ref().map(|ask| ask.as_slice()),
+            },
+        };
+        PriceSlice::new(&self.time_stamps, columns, self.sentiment.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price_ohlc::PriceOhlc;
+    use crate::prices::price_tick::PriceTick;
+
+    #[test]
+    fn push_appends_to_every_column() {
+        let mut series = PriceSeries::new(PriceKind::Ohlc);
+        series.push(&PriceOhlc::new(0, 1.0, 2.0, 0.5, 1.5));
+        series.push(&PriceOhlc::new(60_000, 1.5, 2.5, 1.0, 2.0));
+        let prices = series.as_slice();
+        assert_eq!(series.kind(), PriceKind::Ohlc);
+        assert_eq!(prices.time_stamps(), &[0, 60_000]);
+        assert_eq!(prices.column(&(Tick::Bid, Ohlc::High)), &[2.0, 2.5]);
+        assert_eq!(prices.column(&(Tick::Ask, Ohlc::Low)), &[0.5, 1.0]);
+    }
+
+    fn bar(time_stamp: u64, bid: f32, ask: Option<f32>) -> PriceOhlc {
+        let mut bar = PriceOhlc::new(time_stamp, bid, bid + 0.5, bid - 0.5, bid);
+        if let Some(ask) = ask {
+            bar.apply_spread(&Spread { fixed: Some(ask - bid), hourly: None });
+        }
+        bar
+    }
+
+    #[test]
+    fn spread_applies_to_bars_stored_without_ask_prices() {
+        let spread = Spread { fixed: Some(0.25), hourly: None };
+        let mut series = PriceSeries::new(PriceKind::Ohlc);
+        series.push(&bar(0, 1.0, None));
+        series.push(&bar(60_000, 2.0, None));
+        let ask = series.spread_ask(&spread).unwrap();
+        let prices = series.as_slice().with_ask(ask.as_slice());
+        assert_eq!(prices.column(&(Tick::Ask, Ohlc::Close)), &[1.25, 2.25]);
+        assert_eq!(prices.column(&(Tick::Ask, Ohlc::High)), &[1.75, 2.75]);
+        assert_eq!(prices.column(&(Tick::Bid, Ohlc::Close)), &[1.0, 2.0]);
+
+        // A bar with ask prices equal to its bid had no spread, it is not given one
+        let mut series = PriceSeries::new(PriceKind::Ohlc);
+        series.push(&bar(0, 1.0, Some(1.0)));
+        series.push(&bar(60_000, 2.0, Some(2.5)));
+        assert!(series.spread_ask(&spread).is_none());
+        assert_eq!(series.as_slice().column(&(Tick::Ask, Ohlc::Close)), &[1.0, 2.5]);
+    }
+
+    #[test]
+    fn spread_ask_is_cached_until_the_prices_change() {
+        let spread = Spread { fixed: Some(0.25), hourly: None };
+        let mut series = PriceSeries::new(PriceKind::Ohlc);
+        series.push(&bar(0, 1.0, None));
+        let first = series.spread_ask(&spread).unwrap();
+        assert!(Arc::ptr_eq(&first, &series.spread_ask(&spread).unwrap()));
+        assert!(!Arc::ptr_eq(&first, &series.spread_ask(&Spread { fixed: Some(0.5), hourly: None }).unwrap()));
+
+        series.push(&bar(60_000, 2.0, None));
+        assert_eq!(series.spread_ask(&spread).unwrap().as_slice().close, &[1.25, 2.25]);
+        let mut with_ask = PriceSeries::new(PriceKind::Ohlc);
+        with_ask.push(&bar(0, 1.0, Some(1.5)));
+        assert!(with_ask.spread_ask(&spread).is_none());
+    }
+
+    #[test]
+    fn sentiment_column_is_allocated_with_the_first_sentiment() {
+        let mut series = PriceSeries::new(PriceKind::Tick);
+        series.push(&PriceTick::new(0, 1.0, 1.1, None));
+        assert!(series.as_slice().auxiliary(&Auxiliary::Sentiment).is_none());
+        series.push(&PriceTick::new(1000, 1.0, 1.1, Some(0.6)));
+        let prices = series.as_slice();
+        assert_eq!(prices.get_auxiliary(0, &Auxiliary::Sentiment), None);
+        assert_eq!(prices.get_auxiliary(1, &Auxiliary::Sentiment), Some(0.6));
+    }
+
+    #[test]
+    fn from_columns_checks_column_count_and_length() {
+        assert!(PriceSeries::from_columns(PriceKind::Tick, vec![0, 1], vec![vec![1.0, 1.0]], None).is_err());
+        assert!(PriceSeries::from_columns(PriceKind::Tick, vec![0, 1], vec![vec![1.0, 1.0], vec![1.0]], None).is_err());
+        let series = PriceSeries::from_columns(PriceKind::Ohlc, vec![0], vec![vec![1.0]; 8], Some(vec![0.5])).unwrap();
+        assert_eq!(series.as_slice().get_auxiliary(0, &Auxiliary::Sentiment), Some(0.5));
+    }
+}
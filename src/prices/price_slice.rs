@@ -0,0 +1,237 @@
+use std::ops::{Bound, RangeBounds};
+
+use super::price::{Auxiliary, Ohlc, Price, PriceType, Tick};
+
+#[derive(Debug, Clone, Copy)]
+pub struct OhlcSlice<'a> {
+    pub open: &'a [f32],
+    pub high: &'a [f32],
+    pub low: &'a [f32],
+    pub close: &'a [f32],
+}
+
+impl<'a> OhlcSlice<'a> {
+    pub fn column(&self, ohlc: &Ohlc) -> &'a [f32] {
+        match ohlc {
+            Ohlc::Open => self.open,
+            Ohlc::High => self.high,
+            Ohlc::Low => self.low,
+            Ohlc::Close => self.close,
+        }
+    }
+
+    fn slice(&self, start: usize, end: usize) -> OhlcSlice<'a> {
+        OhlcSlice {
+            open: &self.open[start..end],
+            high: &self.high[start..end],
+            low: &self.low[start..end],
+            close: &self.close[start..end],
+        }
+    }
+
+    fn columns(&self) -> [&'a [f32]; 4] {
+        [self.open, self.high, self.low, self.close]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PriceColumns<'a> {
+    Tick {
+        bid: &'a [f32],
+        ask: &'a [f32],
+    },
+    Ohlc {
+        bid: OhlcSlice<'a>,
+        ask: Option<OhlcSlice<'a>>,
+    },
+}
+
+/// Borrowed view on a contiguous range of a `PriceSeries`.
+#[derive(Debug, Clone, Copy)]
+pub struct PriceSlice<'a> {
+    time_stamps: &'a [u64],
+    columns: PriceColumns<'a>,
+    sentiment: Option<&'a [f32]>,
+}
+
+impl<'a> PriceSlice<'a> {
+    pub fn new(time_stamps: &'a [u64], columns: PriceColumns<'a>, sentiment: Option<&'a [f32]>) -> Self {
+        PriceSlice {
+            time_stamps,
+            columns,
+            sentiment,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.time_stamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time_stamps.is_empty()
+    }
+
+    pub fn time_stamps(&self) -> &'a [u64] {
+        self.time_stamps
+    }
+
+    pub fn columns(&self) -> PriceColumns<'a> {
+        self.columns
+    }
+
+    /// Replaces the ask side of bars, `ask` has to cover the same range as this slice.
+    pub fn with_ask(&self, ask: OhlcSlice<'a>) -> PriceSlice<'a> {
+        match self.columns {
+            PriceColumns::Ohlc { bid, .. } => {
+                assert_eq!(ask.open.len(), self.len(), "Ask columns differ in length");
+                PriceSlice::new(self.time_stamps, PriceColumns::Ohlc { bid, ask: Some(ask) }, self.sentiment)
+            }
+            PriceColumns::Tick { .. } => *self,
+        }
+    }
+
+    pub fn column(&self, price_type: &PriceType) -> &'a [f32] {
+        match self.columns {
+            PriceColumns::Tick { bid, ask } => match price_type.0 {
+                Tick::Ask => ask,
+                Tick::Bid => bid,
+            },
+            PriceColumns::Ohlc { bid, ask } => match (&price_type.0, ask) {
+                (Tick::Ask, Some(ask)) => ask.column(&price_type.1),
+                _ => bid.column(&price_type.1),
+            },
+        }
+    }
+
+    /// Raw price columns in the order expected by `PriceSeries::from_columns`.
+    pub fn raw_columns(&self) -> Vec<&'a [f32]> {
+        match self.columns {
+            PriceColumns::Tick { bid, ask } => vec![bid, ask],
+            PriceColumns::Ohlc { bid, ask } => bid.columns().into_iter().chain(ask.iter().flat_map(|ask| ask.columns())).collect(),
+        }
+    }
+
+    pub fn auxiliary(&self, auxiliary: &Auxiliary) -> Option<&'a [f32]> {
+        match auxiliary {
+            Auxiliary::Sentiment => self.sentiment,
+        }
+    }
+
+    pub fn ts(&self, index: usize) -> u64 {
+        self.time_stamps[index]
+    }
+
+    pub fn get(&self, index: usize, price_type: &PriceType) -> f32 {
+        self.column(price_type)[index]
+    }
+
+    pub fn get_auxiliary(&self, index: usize, auxiliary: &Auxiliary) -> Option<f32> {
+        self.auxiliary(auxiliary)
+            .map(|column| column[index])
+            .filter(|value| !value.is_nan())
+    }
+
+    pub fn at(&self, index: usize) -> PriceRef<'a> {
+        assert!(index < self.len(), "Price index out of bounds");
+        PriceRef {
+            slice: *self,
+            index,
+        }
+    }
+
+    pub fn last(&self) -> Option<PriceRef<'a>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.at(self.len() - 1))
+        }
+    }
+
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> PriceSlice<'a> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        let columns = match self.columns {
+            PriceColumns::Tick { bid, ask } => PriceColumns::Tick {
+                bid: &bid[start..end],
+                ask: &ask[start..end],
+            },
+            PriceColumns::Ohlc { bid, ask } => PriceColumns::Ohlc {
+                bid: bid.slice(start, end),
+                ask: ask.map(|ask| ask.slice(start, end)),
+            },
+        };
+        let sentiment = self.sentiment.map(|column| &column[start..end]);
+        PriceSlice::new(&self.time_stamps[start..end], columns, sentiment)
+    }
+}
+
+/// A single price inside a `PriceSlice`.
+#[derive(Debug, Clone, Copy)]
+pub struct PriceRef<'a> {
+    slice: PriceSlice<'a>,
+    index: usize,
+}
+
+impl Price for PriceRef<'_> {
+    fn get(&self, price_type: &PriceType) -> f32 {
+        self.slice.get(self.index, price_type)
+    }
+
+    fn ts(&self) -> u64 {
+        self.slice.ts(self.index)
+    }
+
+    fn get_auxiliary(&self, auxiliary: &Auxiliary) -> Option<f32> {
+        self.slice.get_auxiliary(self.index, auxiliary)
+    }
+
+    fn has_ask(&self) -> bool {
+        match self.slice.columns {
+            PriceColumns::Tick { .. } => true,
+            PriceColumns::Ohlc { ask, .. } => ask.is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price_series::{PriceKind, PriceSeries};
+    use crate::prices::price_tick::PriceTick;
+
+    fn series() -> PriceSeries {
+        let mut series = PriceSeries::new(PriceKind::Tick);
+        for index in 0..5 {
+            series.push(&PriceTick::new(1000 * index, 1.0 + index as f32, 1.5 + index as f32, None));
+        }
+        series
+    }
+
+    #[test]
+    fn slice_views_the_range_of_every_column() {
+        let series = series();
+        let prices = series.as_slice().slice(1..4);
+        assert_eq!(prices.len(), 3);
+        assert_eq!(prices.time_stamps(), &[1000, 2000, 3000]);
+        assert_eq!(prices.column(&(Tick::Bid, Ohlc::Close)), &[2.0, 3.0, 4.0]);
+        assert_eq!(prices.column(&(Tick::Ask, Ohlc::Close)), &[2.5, 3.5, 4.5]);
+        assert_eq!(prices.slice(..=1).len(), 2);
+    }
+
+    #[test]
+    fn price_ref_reads_its_index() {
+        let series = series();
+        let last = series.as_slice().last().unwrap();
+        assert_eq!(last.ts(), 4000);
+        assert_eq!(last.get(&(Tick::Ask, Ohlc::Open)), 5.5);
+        assert!(series.as_slice().slice(2..2).last().is_none());
+    }
+}
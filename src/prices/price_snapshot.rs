@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+
+use super::price::Auxiliary;
+use super::price_manager::PriceManager;
+use super::price_series::{PriceKind, PriceSeries};
+
+// File layout (little endian):
+//   magic "PBLP" | version u16 | symbol count u32
+//   per symbol: name length u32 | name | kind u8 | price column count u8 | has sentiment u8 | price count u64
+//               | time stamps u64 * count | price columns f32 * count | [sentiment f32 * count] | crc32 u32
+// The checksum covers the whole symbol block up to the checksum itself.
+const MAGIC: &[u8; 4] = b"PBLP";
+const VERSION: u16 = 2;
+
+const KIND_TICK: u8 = 0;
+const KIND_OHLC: u8 = 1;
+
+pub struct PriceSnapshot;
+
+impl PriceSnapshot {
+    pub fn save(path: &str, price_manager: &PriceManager, symbols: Option<&[String]>) -> Result<usize, String> {
+        let mut selected: Vec<(&String, &PriceSeries)> = match symbols {
+            Some(symbols) => symbols.iter()
+                .map(|symbol| price_manager.prices.get_key_value(symbol).ok_or(format!("No prices stored for symbol '{}'", symbol)))
+                .collect::<Result<_, _>>()?,
+            None => price_manager.prices.iter().collect(),
+        };
+        selected.sort_by_key(|(symbol, _)| symbol.as_str());
+
+        let file = File::create(path).map_err(|err| format!("Failed to create '{}': {}", path, err))?;
+        let mut writer = BufWriter::new(file);
+        let io_error = |err: std::io::Error| format!("Failed to write '{}': {}", path, err);
+
+        writer.write_all(MAGIC).map_err(io_error)?;
+        writer.write_all(&VERSION.to_le_bytes()).map_err(io_error)?;
+        writer.write_all(&(selected.len() as u32).to_le_bytes()).map_err(io_error)?;
+        for (symbol, series) in selected.iter() {
+            Self::write_series(&mut writer, symbol, series).map_err(io_error)?;
+        }
+        writer.flush().map_err(io_error)?;
+        Ok(selected.len())
+    }
+
+    pub fn load(path: &str, symbols: Option<&[String]>) -> Result<HashMap<String, PriceSeries>, String> {
+        let file = File::open(path).map_err(|err| format!("Failed to open '{}': {}", path, err))?;
+        let io_error = |err: std::io::Error| format!("Failed to read '{}': {}", path, err);
+        let file_len = file.metadata().map_err(io_error)?.len();
+        let mut reader = BufReader::new(file);
+        // Lengths read from the file are checked against what is left of it before anything is allocated for them
+        let truncated = || format!("Price snapshot '{}' is truncated or corrupt", path);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(io_error)?;
+        if &magic != MAGIC {
+            return Err(format!("'{}' is not a price snapshot", path));
+        }
+        let version = u16::from_le_bytes(Self::read_array(&mut reader).map_err(io_error)?);
+        if version != VERSION {
+            return Err(format!("Unsupported price snapshot version {} in '{}', expected version {}", version, path, VERSION));
+        }
+        let num_symbols = u32::from_le_bytes(Self::read_array(&mut reader).map_err(io_error)?);
+
+        let mut prices = HashMap::new();
+        for _ in 0..num_symbols {
+            let mut reader = ChecksumReader::new(&mut reader);
+            let name_len = u32::from_le_bytes(Self::read_array(&mut reader).map_err(io_error)?) as u64;
+            if name_len > Self::remaining(reader.inner, file_len).map_err(io_error)? {
+                return Err(truncated());
+            }
+            let mut name = vec![0u8; name_len as usize];
+            reader.read_exact(&mut name).map_err(io_error)?;
+            let symbol = String::from_utf8(name).map_err(|_| format!("Invalid symbol name in '{}'", path))?;
+            let [kind, num_price_columns, has_sentiment] = Self::read_array(&mut reader).map_err(io_error)?;
+            let kind = match kind {
+                KIND_TICK => PriceKind::Tick,
+                KIND_OHLC => PriceKind::Ohlc,
+                _ => return Err(format!("Invalid price kind for symbol '{}' in '{}'", symbol, path)),
+            };
+            let count = u64::from_le_bytes(Self::read_array(&mut reader).map_err(io_error)?);
+            let num_columns = num_price_columns as usize + has_sentiment as usize;
+            let block_len = count.checked_mul(8 + 4 * num_columns as u64)
+                .and_then(|len| len.checked_add(4))
+                .ok_or_else(truncated)?;
+            if block_len > Self::remaining(reader.inner, file_len).map_err(io_error)? {
+                return Err(truncated());
+            }
+            let count = count as usize;
+
+            if symbols.is_some_and(|symbols| !symbols.contains(&symbol)) {
+                reader.inner.seek_relative(block_len as i64).map_err(io_error)?;
+                continue;
+            }
+
+            let mut time_stamps = Vec::with_capacity(count);
+            for _ in 0..count {
+                time_stamps.push(u64::from_le_bytes(Self::read_array(&mut reader).map_err(io_error)?));
+            }
+            let mut columns = Vec::with_capacity(num_columns);
+            for _ in 0..num_columns {
+                let mut column = Vec::with_capacity(count);
+                for _ in 0..count {
+                    column.push(f32::from_le_bytes(Self::read_array(&mut reader).map_err(io_error)?));
+                }
+                columns.push(column);
+            }
+            let sentiment = if has_sentiment != 0 { columns.pop() } else { None };
+
+            let checksum = reader.checksum();
+            let stored = u32::from_le_bytes(Self::read_array(&mut reader).map_err(io_error)?);
+            if checksum != stored {
+                return Err(format!("Checksum mismatch for symbol '{}' in '{}'", symbol, path));
+            }
+            let series = PriceSeries::from_columns(kind, time_stamps, columns, sentiment).map_err(|err| err.to_string())?;
+            prices.insert(symbol, series);
+        }
+
+        if let Some(symbols) = symbols {
+            if let Some(missing) = symbols.iter().find(|symbol| !prices.contains_key(*symbol)) {
+                return Err(format!("Symbol '{}' not found in '{}'", missing, path));
+            }
+        }
+        Ok(prices)
+    }
+
+    fn write_series(writer: &mut impl Write, symbol: &str, series: &PriceSeries) -> std::io::Result<()> {
+        let mut writer = ChecksumWriter::new(writer);
+        let prices = series.as_slice();
+        let sentiment = prices.auxiliary(&Auxiliary::Sentiment);
+        let kind = match series.kind() {
+            PriceKind::Tick => KIND_TICK,
+            PriceKind::Ohlc => KIND_OHLC,
+        };
+
+        writer.write_all(&(symbol.len() as u32).to_le_bytes())?;
+        writer.write_all(symbol.as_bytes())?;
+        let columns = prices.raw_columns();
+        writer.write_all(&[kind, columns.len() as u8, sentiment.is_some() as u8])?;
+        writer.write_all(&(prices.len() as u64).to_le_bytes())?;
+        for time_stamp in prices.time_stamps() {
+            writer.write_all(&time_stamp.to_le_bytes())?;
+        }
+        for column in columns.into_iter().chain(sentiment) {
+            for value in column {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        let checksum = writer.checksum();
+        writer.write_all(&checksum.to_le_bytes())
+    }
+
+    fn remaining(reader: &mut BufReader<File>, file_len: u64) -> std::io::Result<u64> {
+        Ok(file_len.saturating_sub(reader.stream_position()?))
+    }
+
+    fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+        let mut buffer = [0u8; N];
+        reader.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+static CRC32_TABLE: once_cell::sync::Lazy<[u32; 256]> = once_cell::sync::Lazy::new(|| {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+        *entry = crc;
+    }
+    table
+});
+
+fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &byte| CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+struct ChecksumWriter<W: Write> {
+    inner: W,
+    crc: u32,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn new(inner: W) -> Self {
+        ChecksumWriter { inner, crc: 0xFFFF_FFFF }
+    }
+
+    fn checksum(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc = crc32_update(self.crc, &buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct ChecksumReader<'a, R: Read> {
+    inner: &'a mut R,
+    crc: u32,
+}
+
+impl<'a, R: Read> ChecksumReader<'a, R> {
+    fn new(inner: &'a mut R) -> Self {
+        ChecksumReader { inner, crc: 0xFFFF_FFFF }
+    }
+
+    fn checksum(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc = crc32_update(self.crc, &buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price_ohlc::PriceOhlc;
+    use crate::prices::price_tick::PriceTick;
+
+    fn path(name: &str) -> String {
+        std::env::temp_dir().join(format!("py_backtest_lab_{}_{}.bin", name, std::process::id())).to_string_lossy().into_owned()
+    }
+
+    fn price_manager() -> PriceManager {
+        let mut price_manager = PriceManager::new();
+        let mut ticks = PriceSeries::new(PriceKind::Tick);
+        ticks.push(&PriceTick::new(0, 1.1, 1.2, Some(0.4)));
+        ticks.push(&PriceTick::new(1000, 1.3, 1.4, None));
+        let mut bars = PriceSeries::new(PriceKind::Ohlc);
+        bars.push(&PriceOhlc::new(0, 1.0, 2.0, 0.5, 1.5));
+        price_manager.prices.insert("EURUSD".to_string(), ticks);
+        price_manager.prices.insert("GBPUSD".to_string(), bars);
+        price_manager
+    }
+
+    fn saved(name: &str) -> (String, Vec<u8>) {
+        let path = path(name);
+        PriceSnapshot::save(&path, &price_manager(), None).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        (path, bytes)
+    }
+
+    #[test]
+    fn round_trip_keeps_all_columns() {
+        let (path, _) = saved("round_trip");
+        let prices = PriceSnapshot::load(&path, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let ticks = prices["EURUSD"].as_slice();
+        assert_eq!(ticks.time_stamps(), &[0, 1000]);
+        assert_eq!(ticks.raw_columns(), price_manager().prices["EURUSD"].as_slice().raw_columns());
+        assert_eq!(ticks.get_auxiliary(0, &Auxiliary::Sentiment), Some(0.4));
+        assert_eq!(ticks.get_auxiliary(1, &Auxiliary::Sentiment), None);
+        assert_eq!(prices["GBPUSD"].kind(), PriceKind::Ohlc);
+    }
+
+    #[test]
+    fn loads_a_subset_of_symbols() {
+        let (path, _) = saved("subset");
+        let prices = PriceSnapshot::load(&path, Some(&["GBPUSD".to_string()])).unwrap();
+        let missing = PriceSnapshot::load(&path, Some(&["USDJPY".to_string()]));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(prices.keys().collect::<Vec<_>>(), vec!["GBPUSD"]);
+        assert!(missing.unwrap_err().contains("not found"));
+    }
+
+    fn load_bytes(name: &str, bytes: &[u8]) -> Result<HashMap<String, PriceSeries>, String> {
+        let path = path(name);
+        std::fs::write(&path, bytes).unwrap();
+        let prices = PriceSnapshot::load(&path, None);
+        std::fs::remove_file(&path).unwrap();
+        prices
+    }
+
+    #[test]
+    fn rejects_other_versions_and_damaged_blocks() {
+        let (path, bytes) = saved("damaged");
+        std::fs::remove_file(&path).unwrap();
+
+        let mut version = bytes.clone();
+        version[4] = 1;
+        assert!(load_bytes("version", &version).unwrap_err().contains("Unsupported price snapshot version"));
+
+        let mut checksum = bytes.clone();
+        let last = checksum.len() - 5;
+        checksum[last] ^= 0xFF;
+        assert!(load_bytes("checksum", &checksum).unwrap_err().contains("Checksum mismatch"));
+
+        assert!(load_bytes("truncated", &bytes[..bytes.len() - 3]).unwrap_err().contains("truncated or corrupt"));
+    }
+
+    #[test]
+    fn rejects_lengths_beyond_the_file() {
+        let (path, bytes) = saved("lengths");
+        std::fs::remove_file(&path).unwrap();
+        // The first symbol block starts after magic, version and symbol count
+        let block = 4 + 2 + 4;
+
+        let mut name_len = bytes.clone();
+        name_len[block..block + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(load_bytes("name_len", &name_len).unwrap_err().contains("truncated or corrupt"));
+
+        let count = block + 4 + "EURUSD".len() + 3;
+        let mut overflow = bytes.clone();
+        overflow[count..count + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(load_bytes("count", &overflow).unwrap_err().contains("truncated or corrupt"));
+    }
+}
@@ -1,6 +1,6 @@
 use dict_derive::{FromPyObject, IntoPyObject};
 
-use super::price::{Price, PriceType, Tick};
+use super::price::{Auxiliary, Price, PriceType, Tick};
 
 
 #[derive(Clone, Debug, FromPyObject, IntoPyObject)]
@@ -8,6 +8,18 @@ pub struct PriceTick {
     time_stamp: u64,
     ask: f32,
     bid: f32,
+    sentiment: Option<f32>,
+}
+
+impl PriceTick {
+    pub fn new(time_stamp: u64, bid: f32, ask: f32, sentiment: Option<f32>) -> Self {
+        PriceTick {
+            time_stamp,
+            ask,
+            bid,
+            sentiment,
+        }
+    }
 }
 
 impl Price for PriceTick {
@@ -21,4 +33,10 @@ impl Price for PriceTick {
     fn ts(&self) -> u64 {
         self.time_stamp
     }
-}
\ No newline at end of file
+
+    fn get_auxiliary(&self, auxiliary: &Auxiliary) -> Option<f32> {
+        match auxiliary {
+            Auxiliary::Sentiment => self.sentiment,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::Timelike;
+use dict_derive::FromPyObject;
+
+/// Spread in price units that is added to the bid side of bars without ask prices.
+/// `hourly` holds one spread per UTC hour and takes precedence over `fixed`.
+#[derive(Clone, Debug, PartialEq, FromPyObject)]
+pub struct Spread {
+    pub fixed: Option<f32>,
+    pub hourly: Option<Vec<f32>>,
+}
+
+impl Spread {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let Some(hourly) = &self.hourly {
+            if hourly.len() != 24 {
+                return Err("Hourly spread profile needs exactly 24 values");
+            }
+        }
+        let valid = |spread: &f32| spread.is_finite() && *spread >= 0.0;
+        if !self.fixed.iter().chain(self.hourly.iter().flatten()).all(valid) {
+            return Err("Spreads have to be finite and at least 0");
+        }
+        Ok(())
+    }
+
+    pub fn at(&self, time_stamp: u64) -> f32 {
+        match &self.hourly {
+            Some(hourly) => {
+                let hour = chrono::DateTime::from_timestamp_millis(time_stamp as i64).map_or(0, |datetime| datetime.hour());
+                hourly[hour as usize]
+            }
+            None => self.fixed.unwrap_or(0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hourly_profile_takes_precedence_over_fixed() {
+        let mut hourly = vec![0.1; 24];
+        hourly[13] = 0.5;
+        let spread = Spread { fixed: Some(0.2), hourly: Some(hourly) };
+        assert_eq!(spread.at(13 * 60 * 60 * 1000 + 1), 0.5);
+        assert_eq!(spread.at(0), 0.1);
+        assert_eq!(Spread { fixed: Some(0.2), hourly: None }.at(0), 0.2);
+        assert_eq!(Spread { fixed: None, hourly: None }.at(0), 0.0);
+    }
+
+    #[test]
+    fn rejects_invalid_spreads() {
+        assert!(Spread { fixed: None, hourly: Some(vec![0.1; 23]) }.validate().is_err());
+        assert!(Spread { fixed: Some(-0.1), hourly: None }.validate().is_err());
+        assert!(Spread { fixed: Some(f32::NAN), hourly: None }.validate().is_err());
+        let mut hourly = vec![0.1; 24];
+        hourly[3] = f32::INFINITY;
+        assert!(Spread { fixed: None, hourly: Some(hourly) }.validate().is_err());
+        assert!(Spread { fixed: Some(0.0), hourly: Some(vec![0.1; 24]) }.validate().is_ok());
+    }
+}
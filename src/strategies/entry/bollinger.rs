@@ -1,4 +1,4 @@
-use crate::{algorithms::Algorithms, evaluation::signal::SignalResult, prices::price::{Ohlc, Price, Tick}};
+use crate::{algorithms::Algorithms, error::BacktestError, evaluation::{order::OrderRequest, signal::SignalResult}, prices::{price::{Ohlc, Tick}, price_slice::PriceSlice}};
 
 use super::entry::Entry;
 
@@ -17,28 +17,18 @@ impl Bollinger {
 }
 
 impl Entry for Bollinger {
-    fn on_init(&self, _signal_result: &mut SignalResult, _prices: &[Box<dyn Price>]) { }
+    fn on_init(&self, _signal_result: &mut SignalResult, _prices: &PriceSlice) { }
     
-    fn check_entry(&self, signal_result: &SignalResult, prices: &[Box<dyn Price>]) -> Option<f32> {
+    /// Limit order at the band, following it as the band moves.
+    fn order(&self, signal_result: &SignalResult, prices: &PriceSlice) -> Result<Option<OrderRequest>, BacktestError> {
         let price_type = if signal_result.signal.action == "buy" {(Tick::Ask, Ohlc::Close)} else {(Tick::Bid, Ohlc::Close)};
         let mean = Algorithms::calculate_mean(&price_type, prices, self.period_minutes as f32);
         let std_dev = Algorithms::calculate_standard_deviation(&price_type, prices, self.period_minutes as f32, mean);
-        if signal_result.signal.action == "buy" {
-            let last_price = prices.last().unwrap().get(&(Tick::Ask, Ohlc::Low));
-            let border = mean - std_dev * self.std_dev_factor;
-            if last_price <= border {
-                Some(border)
-            } else {
-                None
-            }
+        let border = if signal_result.signal.action == "buy" {
+            mean - std_dev * self.std_dev_factor
         } else {
-            let last_price = prices.last().unwrap().get(&(Tick::Bid, Ohlc::High));
-            let border = mean + std_dev * self.std_dev_factor;
-            if last_price >= border {
-                Some(border)
-            } else {
-                None
-            }
-        }
+            mean + std_dev * self.std_dev_factor
+        };
+        Ok(Some(OrderRequest::limit(border)))
     }
 }
\ No newline at end of file
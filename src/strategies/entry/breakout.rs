@@ -0,0 +1,47 @@
+use crate::{algorithms::Algorithms, error::BacktestError, evaluation::{order::OrderRequest, signal::SignalResult}, prices::{price::{Ohlc, Price, Tick}, price_slice::PriceSlice}};
+
+use super::entry::Entry;
+
+const KEY_PRICE_TO_OPEN: &str = "price_to_open";
+const KEY_PRICE_LIMIT: &str = "price_limit";
+
+/// Enters once the price moves `entry_factor` volatilities in the direction of the signal, with a stop order or,
+/// given a `limit_factor`, a stop limit order that does not chase the price beyond it.
+pub struct Breakout {
+    entry_factor: f32,
+    limit_factor: Option<f32>,
+    vol_timeframe: f32,
+}
+
+impl Breakout {
+    pub fn new(entry_factor: f32, limit_factor: Option<f32>, vol_timeframe: f32) -> Breakout {
+        Breakout {
+            entry_factor,
+            limit_factor,
+            vol_timeframe,
+        }
+    }
+}
+
+impl Entry for Breakout {
+    fn on_init(&self, signal_result: &mut SignalResult, prices: &PriceSlice) {
+        let action = signal_result.signal.action.as_str();
+        let price_type  = if action == "buy" {(Tick::Ask, Ohlc::Close)} else {(Tick::Bid, Ohlc::Close)};
+        let volatility = Algorithms::calculate_volatility(&price_type, prices, self.vol_timeframe);
+        let last_price = prices.last().unwrap().get(&price_type);
+        let price_to_open = last_price * Algorithms::calculate_volatility_factor(action, volatility, self.entry_factor);
+        signal_result.position.strategy_attributes.insert(KEY_PRICE_TO_OPEN.to_string(), price_to_open);
+        if let Some(limit_factor) = self.limit_factor {
+            let price_limit = last_price * Algorithms::calculate_volatility_factor(action, volatility, limit_factor);
+            signal_result.position.strategy_attributes.insert(KEY_PRICE_LIMIT.to_string(), price_limit);
+        }
+    }
+
+    fn order(&self, signal_result: &SignalResult, _prices: &PriceSlice) -> Result<Option<OrderRequest>, BacktestError> {
+        let price_to_open = signal_result.position.strategy_attribute(KEY_PRICE_TO_OPEN)?;
+        match self.limit_factor {
+            Some(_) => Ok(Some(OrderRequest::stop_limit(price_to_open, signal_result.position.strategy_attribute(KEY_PRICE_LIMIT)?))),
+            None => Ok(Some(OrderRequest::stop(price_to_open))),
+        }
+    }
+}
@@ -1,6 +1,9 @@
-use crate::{evaluation::signal::SignalResult, prices::price::Price};
+use crate::{error::BacktestError, evaluation::{order::OrderRequest, signal::SignalResult}, prices::price_slice::PriceSlice};
 
+/// Entry of a signal. `prices` reach up to the current price and hold at least one price.
 pub trait Entry: Sync + Send {
-    fn on_init(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]);
-    fn check_entry(&self, signal_result: &SignalResult, prices: &[Box<dyn Price>]) -> Option<f32>;
+    fn on_init(&self, signal_result: &mut SignalResult, prices: &PriceSlice);
+
+    /// Order to enter with at the last price. It replaces the pending order if it differs and cancels it if `None`.
+    fn order(&self, signal_result: &SignalResult, prices: &PriceSlice) -> Result<Option<OrderRequest>, BacktestError>;
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::{evaluation::signal::SignalResult, prices::price::{Ohlc, Price, Tick}};
+use crate::{error::BacktestError, evaluation::{order::OrderRequest, signal::SignalResult}, prices::price_slice::PriceSlice};
 
 use super::entry::Entry;
 
@@ -12,11 +12,9 @@ impl Immediate {
 }
 
 impl Entry for Immediate {
-    fn on_init(&self, _signal_result: &mut SignalResult, _prices: &[Box<dyn Price>]) { }
+    fn on_init(&self, _signal_result: &mut SignalResult, _prices: &PriceSlice) { }
     
-    fn check_entry(&self, signal_result: &SignalResult, prices: &[Box<dyn Price>]) -> Option<f32> {
-        let price_type = if signal_result.signal.action == "buy" {(Tick::Ask, Ohlc::Close)} else {(Tick::Bid, Ohlc::Close)};
-        let last_price = prices.last().unwrap().get(&price_type);
-        Some(last_price)
+    fn order(&self, _signal_result: &SignalResult, _prices: &PriceSlice) -> Result<Option<OrderRequest>, BacktestError> {
+        Ok(Some(OrderRequest::market()))
     }
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 pub mod bollinger;
+pub mod breakout;
+#[allow(clippy::module_inception)]
 pub mod entry;
 pub mod immediate;
 pub mod volatility_mean;
@@ -1,4 +1,4 @@
-use crate::{algorithms::Algorithms, evaluation::signal::SignalResult, prices::price::{Ohlc, Price, Tick}};
+use crate::{algorithms::Algorithms, error::BacktestError, evaluation::{order::OrderRequest, signal::SignalResult}, prices::{price::{Ohlc, Price, Tick}, price_slice::PriceSlice}};
 
 use super::entry::Entry;
 
@@ -19,7 +19,7 @@ impl VolatilityPullback {
 }
 
 impl Entry for VolatilityPullback {
-    fn on_init(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) {
+    fn on_init(&self, signal_result: &mut SignalResult, prices: &PriceSlice) {
         let price_type  = if signal_result.signal.action == "buy" {(Tick::Ask, Ohlc::Close)} else {(Tick::Bid, Ohlc::Close)};
         let volatility = Algorithms::calculate_volatility(&price_type, prices, self.vol_timeframe);
         let last_price = prices.last().unwrap().get(&price_type);
@@ -27,23 +27,7 @@ impl Entry for VolatilityPullback {
         signal_result.position.strategy_attributes.insert(KEY_PRICE_TO_OPEN.to_string(), price_to_open);
     }
     
-    fn check_entry(&self, signal_result: &SignalResult, prices: &[Box<dyn Price>]) -> Option<f32> {
-        if signal_result.signal.action == "buy" {
-            let price = prices.last().unwrap().get(&(Tick::Ask, Ohlc::Low));
-            let border = *signal_result.position.strategy_attributes.get(KEY_PRICE_TO_OPEN).unwrap();
-            if price <= border {
-                Some(border)
-            } else {
-                None
-            }
-        } else {
-            let price = prices.last().unwrap().get(&(Tick::Bid, Ohlc::High));
-            let border = *signal_result.position.strategy_attributes.get(KEY_PRICE_TO_OPEN).unwrap();
-            if price >= border {
-                Some(border)
-            } else {
-                None
-            }
-        }
+    fn order(&self, signal_result: &SignalResult, _prices: &PriceSlice) -> Result<Option<OrderRequest>, BacktestError> {
+        Ok(Some(OrderRequest::limit(signal_result.position.strategy_attribute(KEY_PRICE_TO_OPEN)?)))
     }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::{algorithms::Algorithms, evaluation::signal::SignalResult, prices::price::{Ohlc, Price, Tick}};
+use crate::{algorithms::Algorithms, error::BacktestError, evaluation::signal::SignalResult, prices::{price::{Ohlc, Price, Tick}, price_slice::PriceSlice}};
 
 use super::exit::Exit;
 
@@ -17,37 +17,37 @@ impl Bollinger {
 }
 
 impl Exit for Bollinger {
-    fn on_open(&self, _signal_result: &mut SignalResult, _prices: &[Box<dyn Price>]) { }
+    fn on_open(&self, _signal_result: &mut SignalResult, _prices: &PriceSlice) { }
     
-    fn check_exit(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) -> Option<f32> {
+    fn check_exit(&self, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
         let price_type = if signal_result.signal.action == "buy" {(Tick::Bid, Ohlc::Close)} else {(Tick::Ask, Ohlc::Close)};
         let mean = Algorithms::calculate_mean(&price_type, prices, self.period_minutes as f32);
         let std_dev = Algorithms::calculate_standard_deviation(&price_type, prices, self.period_minutes as f32, mean);
         let index_last_second = {
             let mut index = prices.len() - 1;
-            while index > 0 && prices.last().unwrap().ts() - prices[index].ts() < 60 * 1000 {
+            while index > 0 && prices.last().unwrap().ts() - prices.ts(index) < 60 * 1000 {
                 index -= 1;
             }
             index
         };
 
         let price_type_close = if signal_result.signal.action == "buy" {(Tick::Bid, Ohlc::High)} else {(Tick::Ask, Ohlc::Low)};
-        let mean_1m = Algorithms::calculate_mean(&price_type_close, prices, 2 as f32);
-        let mean_1m_last = Algorithms::calculate_mean(&price_type_close, &prices[index_last_second..], 1 as f32);
+        let mean_1m = Algorithms::calculate_mean(&price_type_close, prices, 2.0);
+        let mean_1m_last = Algorithms::calculate_mean(&price_type_close, &prices.slice(index_last_second..), 1.0);
 
         if signal_result.signal.action == "buy" {
             let border = mean + std_dev * self.std_dev_factor;
             if mean_1m < border && mean_1m_last >= border {
-                Some(border)
+                Ok(Some(border))
             } else {
-                None
+                Ok(None)
             }
         } else {
             let border = mean - std_dev * self.std_dev_factor;
             if mean_1m > border && mean_1m_last <= border {
-                Some(border)
+                Ok(Some(border))
             } else {
-                None
+                Ok(None)
             }
         }
     }
@@ -1,6 +1,17 @@
-use crate::{evaluation::signal::SignalResult, prices::price::Price};
+use crate::{error::BacktestError, evaluation::{signal::SignalResult, order::OrderType}, prices::price_slice::PriceSlice};
 
+/// Exit of an open position. `prices` reach up to the current price and hold at least one price.
 pub trait Exit: Sync + Send {
-    fn on_open(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]);
-    fn check_exit(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) -> Option<f32>;
-}
\ No newline at end of file
+    fn on_open(&self, signal_result: &mut SignalResult, prices: &PriceSlice);
+    fn check_exit(&self, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError>;
+
+    /// Order type of the exit fill, which decides its slippage.
+    fn order_type(&self) -> OrderType {
+        OrderType::Limit
+    }
+
+    /// Take profit levels a scale out closes its fractions at, ordered from the first to the last level.
+    fn take_profit_levels(&self, signal_result: &SignalResult) -> Result<Vec<f32>, BacktestError> {
+        Ok(signal_result.signal.take_profit.clone())
+    }
+}
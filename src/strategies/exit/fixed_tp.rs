@@ -1,4 +1,4 @@
-use crate::{algorithms::Algorithms, evaluation::signal::SignalResult, prices::price::{Ohlc, Price, Tick}};
+use crate::{algorithms::Algorithms, error::BacktestError, evaluation::signal::SignalResult, prices::{price::{Ohlc, Price, Tick}, price_slice::PriceSlice}};
 
 use super::exit::Exit;
 
@@ -16,48 +16,67 @@ impl FixedTP {
             vol_timeframe,
         }
     }
+
+    /// The first level keeps the plain key, further levels are numbered like `take_profit_2`.
+    fn level_key(level: usize) -> String {
+        if level == 0 {
+            KEY_TAKE_PROFIT.to_string()
+        } else {
+            format!("{}_{}", KEY_TAKE_PROFIT, level + 1)
+        }
+    }
 }
 
 impl Exit for FixedTP {
-    fn on_open(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) {
+    fn on_open(&self, signal_result: &mut SignalResult, prices: &PriceSlice) {
         let price_type = if signal_result.signal.action == "buy" {(Tick::Bid, Ohlc::Close)} else {(Tick::Ask, Ohlc::Close)};
         let volatility = Algorithms::calculate_volatility(&price_type, prices, self.vol_timeframe);
         let take_profit_given = {
-            if signal_result.signal.take_profit.len() > 0 {
-                signal_result.signal.take_profit[0]
+            if !signal_result.signal.take_profit.is_empty() {
+                signal_result.signal.take_profit.clone()
             } else {
-                // signal_result.position.closed = true;
                 let price = prices.last().unwrap().get(&price_type);
                 if signal_result.signal.action == "buy" {
-                    price + (price - signal_result.signal.stop_loss) * 2.0
+                    vec![price + (price - signal_result.signal.stop_loss) * 2.0]
                 } else {
-                    price - (signal_result.signal.stop_loss - price) * 2.0
+                    vec![price - (signal_result.signal.stop_loss - price) * 2.0]
                 }
-                
             }
         };
-        let take_profit = take_profit_given * Algorithms::calculate_volatility_factor(signal_result.signal.action.as_str(), volatility, self.tp_factor);
-        signal_result.position.strategy_attributes.insert(KEY_TAKE_PROFIT.to_string(), take_profit);
+        let factor = Algorithms::calculate_volatility_factor(signal_result.signal.action.as_str(), volatility, self.tp_factor);
+        for (level, take_profit) in take_profit_given.into_iter().enumerate() {
+            signal_result.position.strategy_attributes.insert(Self::level_key(level), take_profit * factor);
+        }
     }
     
-    fn check_exit(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) -> Option<f32> {
-        let take_profit = signal_result.position.strategy_attributes.get(KEY_TAKE_PROFIT).expect("Take profit not set");
+    fn check_exit(&self, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
+        // Scale outs close the earlier levels, the rest of the position targets the first level without a fill
+        let levels = self.take_profit_levels(signal_result)?;
+        let take_profit = levels[signal_result.position.fills.len().min(levels.len() - 1)];
         if signal_result.signal.action == "buy" {
             let price = prices.last().unwrap().get(&(Tick::Bid, Ohlc::High));
-            let border = *take_profit;
+            let border = take_profit;
             if price >= border {
-                Some(border)
+                Ok(Some(border))
             } else {
-                None
+                Ok(None)
             }
         } else {
             let price = prices.last().unwrap().get(&(Tick::Ask, Ohlc::Low));
-            let border = *take_profit;
+            let border = take_profit;
             if price <= border {
-                Some(border)
+                Ok(Some(border))
             } else {
-                None
+                Ok(None)
             }
         }
     }
+
+    fn take_profit_levels(&self, signal_result: &SignalResult) -> Result<Vec<f32>, BacktestError> {
+        let mut levels = vec![signal_result.position.strategy_attribute(KEY_TAKE_PROFIT)?];
+        while let Some(&take_profit) = signal_result.position.strategy_attributes.get(&Self::level_key(levels.len())) {
+            levels.push(take_profit);
+        }
+        Ok(levels)
+    }
 }
\ No newline at end of file
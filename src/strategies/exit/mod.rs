@@ -1,4 +1,5 @@
 pub mod bollinger;
+#[allow(clippy::module_inception)]
 pub mod exit;
 pub mod fixed_tp;
 pub mod trailing_stop;
\ No newline at end of file
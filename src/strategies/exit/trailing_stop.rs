@@ -1,4 +1,4 @@
-use crate::{evaluation::signal::SignalResult, prices::price::{Ohlc, Price, Tick}};
+use crate::{error::BacktestError, evaluation::{signal::SignalResult, order::OrderType}, prices::{price::{Ohlc, Price, Tick}, price_slice::PriceSlice}};
 
 use super::exit::Exit;
 
@@ -18,14 +18,15 @@ impl TrailingStop {
 }
 
 impl Exit for TrailingStop {
-    fn on_open(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) {
+    fn on_open(&self, signal_result: &mut SignalResult, prices: &PriceSlice) {
         let price_type = if signal_result.signal.action == "buy" {(Tick::Bid, Ohlc::High)} else {(Tick::Ask, Ohlc::Low)};
         let price = prices.last().unwrap().get(&price_type);
         signal_result.position.strategy_attributes.insert(KEY_EXTREME.to_string(), price);
     }
     
-    fn check_exit(&self, signal_result: &mut SignalResult, prices: &[Box<dyn Price>]) -> Option<f32> {
-        let mut extreme = *signal_result.position.strategy_attributes.get(KEY_EXTREME).expect("High not set");
+    fn check_exit(&self, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
+        let mut extreme = signal_result.position.strategy_attribute(KEY_EXTREME)?;
+        let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
         let price_type = if signal_result.signal.action == "buy" {(Tick::Bid, Ohlc::High)} else {(Tick::Ask, Ohlc::Low)};
         let price = prices.last().unwrap().get(&price_type);
         if (signal_result.signal.action == "buy"  && price > extreme) || (signal_result.signal.action == "sell" && price < extreme) {
@@ -41,23 +42,27 @@ impl Exit for TrailingStop {
         if signal_result.signal.action == "buy" {
             let price_type_check = (Tick::Bid, Ohlc::Low);
             let price_check = prices.last().unwrap().get(&price_type_check);
-            if price_check < signal_result.position.price_open.unwrap() || stop_loss < signal_result.position.price_open.unwrap() {
-                return None;
+            if price_check < price_open || stop_loss < price_open {
+                return Ok(None);
             }
         } else {
             let price_type_check = (Tick::Ask, Ohlc::High);
             let price_check = prices.last().unwrap().get(&price_type_check);
-            if price_check > signal_result.position.price_open.unwrap() || stop_loss > signal_result.position.price_open.unwrap() {
-                return None;
+            if price_check > price_open || stop_loss > price_open {
+                return Ok(None);
             }
         }
         
         let price_type_check = if signal_result.signal.action == "buy" {(Tick::Bid, Ohlc::Low)} else {(Tick::Ask, Ohlc::High)};
         let price_check = prices.last().unwrap().get(&price_type_check);
         if (signal_result.signal.action == "buy" && price_check <= stop_loss) || (signal_result.signal.action == "sell" && price_check >= stop_loss) {
-            Some(stop_loss)
+            Ok(Some(stop_loss))
             } else {
-           None
+           Ok(None)
         }
     }
+
+    fn order_type(&self) -> OrderType {
+        OrderType::Stop
+    }
 }
\ No newline at end of file
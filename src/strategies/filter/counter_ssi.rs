@@ -0,0 +1,75 @@
+use crate::{evaluation::signal::Signal, prices::{price::{Auxiliary, Price}, price_slice::PriceSlice}};
+
+use super::filter::Filter;
+
+/// Trades against the crowd: rejects buys while the share of long retail traders is at or above `threshold_long`
+/// and sells while it is at or below `threshold_short`. Signals without sentiment data pass.
+pub struct CounterSsi {
+    threshold_long: f32,
+    threshold_short: f32,
+}
+
+impl CounterSsi {
+    pub fn new(threshold_long: f32, threshold_short: f32) -> CounterSsi {
+        CounterSsi {
+            threshold_long,
+            threshold_short,
+        }
+    }
+}
+
+impl Filter for CounterSsi {
+    fn check_filter(&self, signal: &Signal, prices: &PriceSlice) -> bool {
+        let sentiment = match prices.last().and_then(|price| price.get_auxiliary(&Auxiliary::Sentiment)) {
+            Some(sentiment) => sentiment,
+            None => return false,
+        };
+        if signal.action == "buy" {
+            sentiment >= self.threshold_long
+        } else {
+            sentiment <= self.threshold_short
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::price_series::{PriceKind, PriceSeries};
+    use crate::prices::price_tick::PriceTick;
+
+    fn signal(action: &str) -> Signal {
+        Signal {
+            id: None,
+            symbol: "EURUSD".to_string(),
+            action: action.to_string(),
+            stop_loss: 1.0,
+            take_profit: vec![1.2],
+            time_stamp: 0,
+            source: "A".to_string(),
+        }
+    }
+
+    fn prices(sentiment: Option<f32>) -> PriceSeries {
+        let mut series = PriceSeries::new(PriceKind::Tick);
+        series.push(&PriceTick::new(0, 1.1, 1.1, sentiment));
+        series
+    }
+
+    #[test]
+    fn rejects_signals_with_the_crowd() {
+        let filter = CounterSsi::new(0.6, 0.4);
+        let crowd_long = prices(Some(0.7));
+        assert!(filter.check_filter(&signal("buy"), &crowd_long.as_slice()));
+        assert!(!filter.check_filter(&signal("sell"), &crowd_long.as_slice()));
+        let crowd_short = prices(Some(0.3));
+        assert!(!filter.check_filter(&signal("buy"), &crowd_short.as_slice()));
+        assert!(filter.check_filter(&signal("sell"), &crowd_short.as_slice()));
+    }
+
+    #[test]
+    fn passes_signals_without_sentiment() {
+        let filter = CounterSsi::new(0.0, 1.0);
+        assert!(!filter.check_filter(&signal("buy"), &prices(None).as_slice()));
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{evaluation::signal::Signal, prices::price::Price};
+use crate::{evaluation::signal::Signal, prices::price_slice::PriceSlice};
 
 pub trait Filter: Sync + Send {
-    fn check_filter(&self,  signal_result: &Signal, prices: &[Box<dyn Price>]) -> bool;
+    fn check_filter(&self,  signal_result: &Signal, prices: &PriceSlice) -> bool;
 }
\ No newline at end of file
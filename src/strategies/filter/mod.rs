@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod filter;
 pub mod no_filter;
-// pub mod counter_ssi;
\ No newline at end of file
+pub mod counter_ssi;
@@ -1,4 +1,4 @@
-use crate::{evaluation::signal::Signal, prices::price::Price};
+use crate::{evaluation::signal::Signal, prices::price_slice::PriceSlice};
 
 use super::filter::Filter;
 
@@ -12,7 +12,7 @@ impl NoFilter {
 }
 
 impl Filter for NoFilter {
-    fn check_filter(&self, _signal: &Signal, _prices: &[Box<dyn Price>]) -> bool {
+    fn check_filter(&self, _signal: &Signal, _prices: &PriceSlice) -> bool {
         false
     }
 }
\ No newline at end of file
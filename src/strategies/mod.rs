@@ -1,4 +1,7 @@
+pub mod scale_out;
+pub mod sizer;
+pub mod stop_manager;
 pub mod strategy;
 mod entry;
 mod exit;
-mod filter;
\ No newline at end of file
+mod filter;
@@ -0,0 +1,158 @@
+use dict_derive::FromPyObject;
+
+use crate::error::BacktestError;
+use crate::evaluation::position::{PositionFill, SIZE_EPSILON};
+use crate::evaluation::signal::SignalResult;
+use crate::evaluation::order::OrderType;
+use crate::evaluation::slippage::Slippage;
+use crate::prices::price::{Ohlc, Price, Tick};
+use crate::prices::price_slice::PriceSlice;
+use super::exit::exit::Exit;
+
+/// Scale out as given in the strategy rules, `fractions[i]` of the position is closed at take profit level `i`.
+#[derive(Clone, Debug, FromPyObject)]
+pub struct ScaleOutRules {
+    fractions: Vec<f32>,
+    stop_move: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StopMove {
+    None,
+    BreakEven,
+    PreviousLevel,
+}
+
+impl StopMove {
+    fn parse(stop_move: &str) -> Result<Self, String> {
+        match stop_move {
+            "none" => Ok(StopMove::None),
+            "break_even" => Ok(StopMove::BreakEven),
+            "previous_level" => Ok(StopMove::PreviousLevel),
+            _ => Err(format!("Invalid stop move '{}', expected 'none', 'break_even' or 'previous_level'", stop_move)),
+        }
+    }
+}
+
+/// Closes a position in parts at the take profit levels of its exit strategy. Whatever is left after the last
+/// fraction is closed by the exit strategy itself.
+pub struct ScaleOut {
+    fractions: Vec<f32>,
+    stop_move: StopMove,
+}
+
+impl ScaleOut {
+    pub fn new(rules: &ScaleOutRules) -> Result<Self, String> {
+        if let Some(fraction) = rules.fractions.iter().find(|fraction| !(**fraction > 0.0 && **fraction <= 1.0)) {
+            return Err(format!("Invalid scale out fraction {}, expected a value above 0 and up to 1", fraction));
+        }
+        if rules.fractions.iter().sum::<f32>() > 1.0 + SIZE_EPSILON {
+            return Err("Scale out fractions add up to more than 1".to_string());
+        }
+        Ok(ScaleOut {
+            fractions: rules.fractions.clone(),
+            stop_move: rules.stop_move.as_deref().map_or(Ok(StopMove::None), StopMove::parse)?,
+        })
+    }
+
+    /// Fills the fractions of all levels the last price reached and returns the close price once nothing is left.
+    /// The stop loss is only moved if that tightens it.
+    pub fn check_partial_exits(&self, exit: &dyn Exit, slippage: &Slippage, signal_result: &mut SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
+        let last_price = prices.last().ok_or_else(|| BacktestError::missing_price(&signal_result.signal.symbol))?;
+        let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
+        let levels = exit.take_profit_levels(signal_result)?;
+        let buy = signal_result.signal.action == "buy";
+        let price = if buy { last_price.get(&(Tick::Bid, Ohlc::High)) } else { last_price.get(&(Tick::Ask, Ohlc::Low)) };
+
+        while let (Some(&fraction), Some(&level)) = (self.fractions.get(signal_result.position.fills.len()), levels.get(signal_result.position.fills.len())) {
+            if (buy && price < level) || (!buy && price > level) {
+                break;
+            }
+            let price_fill = slippage.apply(OrderType::Limit, signal_result, prices, level, false);
+            let position = &mut signal_result.position;
+            let filled = position.fills.len();
+            position.fills.push(PositionFill::new(last_price.ts(), price_fill, fraction.min(position.size_open())));
+            // A stop already moved further, e.g. by a stop manager, is kept
+            match self.stop_move {
+                StopMove::None => {}
+                StopMove::BreakEven => signal_result.tighten_stop(last_price.ts(), price_open, "scale_out"),
+                StopMove::PreviousLevel => signal_result.tighten_stop(last_price.ts(), if filled == 0 { price_open } else { levels[filled - 1] }, "scale_out"),
+            }
+            if signal_result.position.size_open() <= SIZE_EPSILON {
+                return Ok(Some(price_fill));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+impl ScaleOutRules {
+    pub fn new(fractions: &[f32], stop_move: Option<&str>) -> Self {
+        ScaleOutRules {
+            fractions: fractions.to_vec(),
+            stop_move: stop_move.map(str::to_string),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+    use crate::strategies::exit::fixed_tp::FixedTP;
+
+    /// Position opened at 1.1 on the first bar of `prices` with take profits at 1.2 and 1.3.
+    fn opened(exit: &FixedTP, prices: &PriceSlice) -> SignalResult {
+        let mut signal_result = SignalResult::new(fixtures::signal("buy", 1.0, 1.2, 0));
+        signal_result.signal.take_profit.push(1.3);
+        signal_result.position.price_open = Some(1.1);
+        exit.on_open(&mut signal_result, &prices.slice(..1));
+        signal_result
+    }
+
+    fn check(scale_out: &ScaleOut, exit: &FixedTP, signal_result: &mut SignalResult, prices: &PriceSlice, p: usize) -> Option<f32> {
+        scale_out.check_partial_exits(exit, &Slippage::default(), signal_result, &prices.slice(..p + 1)).unwrap()
+    }
+
+    #[test]
+    fn fills_fractions_at_the_levels_reached() {
+        let series = fixtures::bars(&[(1.1, 1.1, 1.1, 1.1), (1.1, 1.25, 1.1, 1.2), (1.2, 1.35, 1.2, 1.3)]);
+        let prices = series.as_slice();
+        let exit = FixedTP::new(0.0, 60.0);
+        let scale_out = ScaleOut::new(&ScaleOutRules::new(&[0.5, 0.3], Some("break_even"))).unwrap();
+        let mut signal_result = opened(&exit, &prices);
+
+        assert_eq!(check(&scale_out, &exit, &mut signal_result, &prices, 0), None);
+        assert!(signal_result.position.fills.is_empty());
+        assert_eq!(check(&scale_out, &exit, &mut signal_result, &prices, 1), None);
+        assert_eq!(check(&scale_out, &exit, &mut signal_result, &prices, 2), None);
+        let fills: Vec<(f32, f32)> = signal_result.position.fills.iter().map(|fill| (fill.price, fill.size)).collect();
+        assert_eq!(fills, vec![(1.2, 0.5), (1.3, 0.3)]);
+        assert!((signal_result.position.size_open() - 0.2).abs() < SIZE_EPSILON);
+        assert_eq!(signal_result.stop_loss(), 1.1);
+        assert_eq!(signal_result.position.stop_changes.len(), 1);
+    }
+
+    #[test]
+    fn closes_once_nothing_is_left() {
+        let series = fixtures::bars(&[(1.1, 1.1, 1.1, 1.1), (1.1, 1.35, 1.1, 1.3)]);
+        let prices = series.as_slice();
+        let exit = FixedTP::new(0.0, 60.0);
+        let scale_out = ScaleOut::new(&ScaleOutRules::new(&[0.5, 0.5], Some("previous_level"))).unwrap();
+        let mut signal_result = opened(&exit, &prices);
+
+        assert_eq!(check(&scale_out, &exit, &mut signal_result, &prices, 1), Some(1.3));
+        assert_eq!(signal_result.position.fills.len(), 2);
+        assert_eq!(signal_result.stop_loss(), 1.2);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(ScaleOut::new(&ScaleOutRules::new(&[0.7, 0.7], None)).is_err());
+        assert!(ScaleOut::new(&ScaleOutRules::new(&[0.0], None)).is_err());
+        assert!(ScaleOut::new(&ScaleOutRules::new(&[1.5], None)).is_err());
+        assert!(ScaleOut::new(&ScaleOutRules::new(&[0.5], Some("trailing"))).is_err());
+        assert!(ScaleOut::new(&ScaleOutRules::new(&[0.5, 0.5], None)).is_ok());
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{error::BacktestError, evaluation::signal::SignalResult, prices::price_slice::PriceSlice};
+
+use super::sizer::{Sizer, SizingContext};
+
+/// Opens positions worth `percent` of the equity.
+pub struct EquityPercent {
+    percent: f32,
+}
+
+impl EquityPercent {
+    pub fn new(percent: f32) -> EquityPercent {
+        EquityPercent {
+            percent,
+        }
+    }
+}
+
+impl Sizer for EquityPercent {
+    fn size(&self, signal_result: &SignalResult, _prices: &PriceSlice, context: &SizingContext) -> Result<f32, BacktestError> {
+        let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
+        Ok(context.equity * self.percent / 100.0 / (price_open * context.contract_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn positions_are_worth_the_percent_of_the_equity() {
+        let series = fixtures::flat_bars(&[1.25]);
+        let lots = EquityPercent::new(50.0).size(&fixtures::opened_at(1.25), &series.as_slice(), &fixtures::sizing_context()).unwrap();
+        assert!((lots * 1.25 * 100_000.0 - 5_000.0).abs() < 1e-2);
+    }
+}
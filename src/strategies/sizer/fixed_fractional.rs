@@ -0,0 +1,45 @@
+use crate::{error::BacktestError, evaluation::signal::SignalResult, prices::price_slice::PriceSlice};
+
+use super::sizer::{Sizer, SizingContext};
+
+/// Risks `risk_fraction` of the equity on the distance from the open price to the stop loss of the signal. A stop loss
+/// at the open price risks nothing, so no lots are traded and the engine rejects the entry.
+pub struct FixedFractional {
+    risk_fraction: f32,
+}
+
+impl FixedFractional {
+    pub fn new(risk_fraction: f32) -> FixedFractional {
+        FixedFractional {
+            risk_fraction,
+        }
+    }
+}
+
+impl Sizer for FixedFractional {
+    fn size(&self, signal_result: &SignalResult, _prices: &PriceSlice, context: &SizingContext) -> Result<f32, BacktestError> {
+        let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
+        let distance = (price_open - signal_result.signal.stop_loss).abs();
+        if distance == 0.0 {
+            return Ok(0.0);
+        }
+        Ok(context.equity * self.risk_fraction / (distance * context.contract_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn risks_the_fraction_on_the_stop_loss_distance() {
+        let series = fixtures::flat_bars(&[1.1]);
+        let sizer = FixedFractional::new(0.01);
+        // Losing 0.1 per unit on 100000 units per lot, 100 of equity are risked with 0.01 lots
+        let lots = sizer.size(&fixtures::opened_at(1.1), &series.as_slice(), &fixtures::sizing_context()).unwrap();
+        assert!((lots - 0.01).abs() < 1e-6);
+        assert_eq!(sizer.size(&fixtures::opened_at(1.0), &series.as_slice(), &fixtures::sizing_context()).unwrap(), 0.0);
+        assert!(sizer.size(&SignalResult::new(fixtures::signal("buy", 1.0, 1.2, 0)), &series.as_slice(), &fixtures::sizing_context()).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{error::BacktestError, evaluation::signal::SignalResult, prices::price_slice::PriceSlice};
+
+use super::sizer::{Sizer, SizingContext};
+
+/// Trades the same lots for every position, by default the lot size of the backtest conditions.
+pub struct FixedLots {
+    lots: Option<f32>,
+}
+
+impl FixedLots {
+    pub fn new(lots: Option<f32>) -> FixedLots {
+        FixedLots {
+            lots,
+        }
+    }
+}
+
+impl Sizer for FixedLots {
+    fn size(&self, _signal_result: &SignalResult, _prices: &PriceSlice, context: &SizingContext) -> Result<f32, BacktestError> {
+        Ok(self.lots.unwrap_or(context.lot_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn trades_the_given_lots_or_the_lot_size() {
+        let series = fixtures::flat_bars(&[1.1]);
+        let signal_result = SignalResult::new(fixtures::signal("buy", 1.0, 1.2, 0));
+        let context = SizingContext { equity: 10_000.0, lot_size: 0.1, contract_size: 100_000.0 };
+        assert_eq!(FixedLots::new(None).size(&signal_result, &series.as_slice(), &context).unwrap(), 0.1);
+        assert_eq!(FixedLots::new(Some(2.0)).size(&signal_result, &series.as_slice(), &context).unwrap(), 2.0);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod equity_percent;
+pub mod fixed_fractional;
+pub mod fixed_lots;
+#[allow(clippy::module_inception)]
+pub mod sizer;
+pub mod volatility_target;
@@ -0,0 +1,14 @@
+use crate::{error::BacktestError, evaluation::signal::SignalResult, prices::price_slice::PriceSlice};
+
+/// Account state a sizer decides on, `equity` is the starting equity plus the profit closed so far.
+pub struct SizingContext {
+    pub equity: f32,
+    pub lot_size: f32,
+    pub contract_size: f32,
+}
+
+pub trait Sizer: Sync + Send {
+    /// Lots to trade for a position that is opened at `price_open` of `signal_result`. Lots that are not a positive
+    /// number reject the entry.
+    fn size(&self, signal_result: &SignalResult, prices: &PriceSlice, context: &SizingContext) -> Result<f32, BacktestError>;
+}
@@ -0,0 +1,51 @@
+use crate::{algorithms::Algorithms, error::BacktestError, evaluation::signal::SignalResult, prices::{price::{Ohlc, Tick}, price_slice::PriceSlice}};
+
+use super::sizer::{Sizer, SizingContext};
+
+/// Sizes positions so that their volatility over `vol_timeframe` minutes is `target_volatility` of the equity.
+/// Without enough prices to measure the volatility, the lot size of the backtest conditions is used.
+pub struct VolatilityTarget {
+    target_volatility: f32,
+    vol_timeframe: f32,
+}
+
+impl VolatilityTarget {
+    pub fn new(target_volatility: f32, vol_timeframe: f32) -> VolatilityTarget {
+        VolatilityTarget {
+            target_volatility,
+            vol_timeframe,
+        }
+    }
+}
+
+impl Sizer for VolatilityTarget {
+    fn size(&self, signal_result: &SignalResult, prices: &PriceSlice, context: &SizingContext) -> Result<f32, BacktestError> {
+        let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
+        let price_type = if signal_result.signal.action == "buy" {(Tick::Ask, Ohlc::Close)} else {(Tick::Bid, Ohlc::Close)};
+        let volatility = Algorithms::calculate_volatility(&price_type, prices, self.vol_timeframe);
+        if volatility <= 0.0 {
+            return Ok(context.lot_size);
+        }
+        Ok(context.equity * self.target_volatility / (volatility * price_open * context.contract_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn sizes_by_the_volatility_or_falls_back_to_the_lot_size() {
+        let sizer = VolatilityTarget::new(0.01, 60.0);
+        let flat = fixtures::flat_bars(&[1.1; 20]);
+        assert_eq!(sizer.size(&fixtures::opened_at(1.1), &flat.as_slice(), &fixtures::sizing_context()).unwrap(), 1.0);
+
+        let closes: Vec<f32> = (0..20).map(|minute| if minute % 2 == 0 { 1.1 } else { 1.2 }).collect();
+        let moving = fixtures::flat_bars(&closes);
+        let volatility = Algorithms::calculate_volatility(&(Tick::Ask, Ohlc::Close), &moving.as_slice(), 60.0);
+        assert!(volatility > 0.0);
+        let lots = sizer.size(&fixtures::opened_at(1.1), &moving.as_slice(), &fixtures::sizing_context()).unwrap();
+        assert!((lots * volatility * 1.1 * 100_000.0 - 100.0).abs() < 1e-2);
+    }
+}
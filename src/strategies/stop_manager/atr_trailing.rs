@@ -0,0 +1,55 @@
+use crate::{algorithms::Algorithms, error::BacktestError, evaluation::signal::SignalResult, prices::{price::{Ohlc, Price, Tick}, price_slice::PriceSlice}};
+
+use super::stop_manager::StopManager;
+
+/// Trails the stop loss `atr_factor` average true ranges over `period_minutes` behind the best price.
+pub struct AtrTrailing {
+    atr_factor: f32,
+    period_minutes: f32,
+}
+
+impl AtrTrailing {
+    pub fn new(atr_factor: f32, period_minutes: f32) -> AtrTrailing {
+        AtrTrailing {
+            atr_factor,
+            period_minutes,
+        }
+    }
+}
+
+impl StopManager for AtrTrailing {
+    fn name(&self) -> &'static str {
+        "atr_trailing"
+    }
+
+    fn update(&self, signal_result: &SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
+        let last_price = prices.last().ok_or_else(|| BacktestError::missing_price(&signal_result.signal.symbol))?;
+        let distance = self.atr_factor * Algorithms::calculate_average_true_range(prices, self.period_minutes);
+        if distance <= 0.0 {
+            return Ok(None);
+        }
+        if signal_result.signal.action == "buy" {
+            Ok(Some(last_price.get(&(Tick::Bid, Ohlc::High)) - distance))
+        } else {
+            Ok(Some(last_price.get(&(Tick::Ask, Ohlc::Low)) + distance))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn trails_the_best_price_by_the_average_true_range() {
+        let stop_manager = AtrTrailing::new(2.0, 60.0);
+        // Both bars have a true range of 0.04
+        let series = fixtures::bars(&[(1.0, 1.02, 0.98, 1.0), (1.0, 1.02, 0.98, 1.0), (1.0, 1.03, 0.99, 1.01)]);
+        let stop_loss = stop_manager.update(&fixtures::opened("buy", 1.0, 0.9), &series.as_slice()).unwrap().unwrap();
+        assert!((stop_loss - 0.95).abs() < 1e-5);
+        let stop_loss = stop_manager.update(&fixtures::opened("sell", 1.0, 1.1), &series.as_slice()).unwrap().unwrap();
+        assert!((stop_loss - 1.07).abs() < 1e-5);
+        assert_eq!(stop_manager.update(&fixtures::opened("buy", 1.0, 0.9), &fixtures::flat_bars(&[1.0, 1.0]).as_slice()).unwrap(), None);
+    }
+}
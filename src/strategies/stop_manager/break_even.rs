@@ -0,0 +1,50 @@
+use crate::{error::BacktestError, evaluation::signal::SignalResult, prices::price_slice::PriceSlice};
+
+use super::stop_manager::{excursion, price_from_open, risk, StopManager};
+
+/// Moves the stop loss to the open price plus `offset_r` times the risk once the price moved `trigger_r` times the risk.
+pub struct BreakEven {
+    trigger_r: f32,
+    offset_r: f32,
+}
+
+impl BreakEven {
+    pub fn new(trigger_r: f32, offset_r: f32) -> BreakEven {
+        BreakEven {
+            trigger_r,
+            offset_r,
+        }
+    }
+}
+
+impl StopManager for BreakEven {
+    fn name(&self) -> &'static str {
+        "break_even"
+    }
+
+    fn update(&self, signal_result: &SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
+        let risk = risk(signal_result)?;
+        if risk == 0.0 || excursion(signal_result, prices)? < self.trigger_r * risk {
+            return Ok(None);
+        }
+        Ok(Some(price_from_open(signal_result, self.offset_r * risk)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn moves_to_the_open_once_the_trigger_is_reached() {
+        let stop_manager = BreakEven::new(1.0, 0.0);
+        let buy = fixtures::opened("buy", 1.1, 1.0);
+        assert_eq!(stop_manager.update(&buy, &fixtures::flat_bars(&[1.15]).as_slice()).unwrap(), None);
+        assert_eq!(stop_manager.update(&buy, &fixtures::flat_bars(&[1.25]).as_slice()).unwrap(), Some(1.1));
+
+        let sell = fixtures::opened("sell", 1.1, 1.2);
+        assert_eq!(BreakEven::new(1.0, 0.5).update(&sell, &fixtures::flat_bars(&[0.95]).as_slice()).unwrap(), Some(1.05));
+        assert!(stop_manager.update(&SignalResult::new(fixtures::signal("buy", 1.0, 1.2, 0)), &fixtures::flat_bars(&[1.25]).as_slice()).is_err());
+    }
+}
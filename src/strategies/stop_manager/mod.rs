@@ -0,0 +1,5 @@
+pub mod atr_trailing;
+pub mod break_even;
+pub mod profit_lock;
+#[allow(clippy::module_inception)]
+pub mod stop_manager;
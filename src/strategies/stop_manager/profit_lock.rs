@@ -0,0 +1,59 @@
+use crate::{error::BacktestError, evaluation::signal::SignalResult, prices::price_slice::PriceSlice};
+
+use super::stop_manager::{excursion, price_from_open, risk, StopManager};
+
+/// Locks `lock_fraction` of the profit in steps, e.g. with `step_r` 1 and `lock_fraction` 0.5 the stop loss is at
+/// 0.5 times the risk over the open price once the price moved 1 times the risk, at 1 times the risk after 2 and so on.
+pub struct ProfitLock {
+    step_r: f32,
+    lock_fraction: f32,
+}
+
+impl ProfitLock {
+    pub fn new(step_r: f32, lock_fraction: f32) -> ProfitLock {
+        ProfitLock {
+            step_r,
+            lock_fraction,
+        }
+    }
+}
+
+impl StopManager for ProfitLock {
+    fn name(&self) -> &'static str {
+        "profit_lock"
+    }
+
+    fn update(&self, signal_result: &SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError> {
+        let step = self.step_r * risk(signal_result)?;
+        if step <= 0.0 {
+            return Ok(None);
+        }
+        let steps = (excursion(signal_result, prices)? / step).floor();
+        if steps < 1.0 {
+            return Ok(None);
+        }
+        Ok(Some(price_from_open(signal_result, steps * step * self.lock_fraction)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::fixtures;
+
+    #[test]
+    fn locks_a_fraction_of_every_full_step() {
+        let stop_manager = ProfitLock::new(1.0, 0.5);
+        let buy = fixtures::opened("buy", 1.1, 1.0);
+        assert_eq!(stop_manager.update(&buy, &fixtures::flat_bars(&[1.18]).as_slice()).unwrap(), None);
+        let stop_loss = stop_manager.update(&buy, &fixtures::flat_bars(&[1.25]).as_slice()).unwrap().unwrap();
+        assert!((stop_loss - 1.15).abs() < 1e-5);
+        let stop_loss = stop_manager.update(&buy, &fixtures::flat_bars(&[1.32]).as_slice()).unwrap().unwrap();
+        assert!((stop_loss - 1.2).abs() < 1e-5);
+
+        let sell = fixtures::opened("sell", 1.1, 1.2);
+        let stop_loss = stop_manager.update(&sell, &fixtures::flat_bars(&[0.95]).as_slice()).unwrap().unwrap();
+        assert!((stop_loss - 1.05).abs() < 1e-5);
+        assert_eq!(ProfitLock::new(1.0, 0.5).update(&fixtures::opened("buy", 1.1, 1.1), &fixtures::flat_bars(&[1.5]).as_slice()).unwrap(), None);
+    }
+}
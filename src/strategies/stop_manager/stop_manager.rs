@@ -0,0 +1,33 @@
+use crate::{error::BacktestError, evaluation::signal::SignalResult, prices::{price::{Ohlc, Price, Tick}, price_slice::PriceSlice}};
+
+pub trait StopManager: Sync + Send {
+    /// Recorded as the reason of the stop changes it makes.
+    fn name(&self) -> &'static str;
+
+    /// Stop loss of the open position from the next price on, `None` keeps the current one. The engine only takes stops
+    /// that are tighter than the current one.
+    fn update(&self, signal_result: &SignalResult, prices: &PriceSlice) -> Result<Option<f32>, BacktestError>;
+}
+
+/// Distance from the open price to the stop loss of the signal, the `R` that stops are moved in multiples of.
+pub fn risk(signal_result: &SignalResult) -> Result<f32, BacktestError> {
+    let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
+    Ok((price_open - signal_result.signal.stop_loss).abs())
+}
+
+/// How far the best side of the last price moved in favour of the position since its open.
+pub fn excursion(signal_result: &SignalResult, prices: &PriceSlice) -> Result<f32, BacktestError> {
+    let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
+    let last_price = prices.last().ok_or_else(|| BacktestError::missing_price(&signal_result.signal.symbol))?;
+    if signal_result.signal.action == "buy" {
+        Ok(last_price.get(&(Tick::Bid, Ohlc::High)) - price_open)
+    } else {
+        Ok(price_open - last_price.get(&(Tick::Ask, Ohlc::Low)))
+    }
+}
+
+/// Price `distance` away from the open price in favour of the position.
+pub fn price_from_open(signal_result: &SignalResult, distance: f32) -> Result<f32, BacktestError> {
+    let price_open = signal_result.position.price_open.ok_or(BacktestError::StrategyState("Price open not set".to_string()))?;
+    Ok(if signal_result.signal.action == "buy" { price_open + distance } else { price_open - distance })
+}
@@ -2,8 +2,12 @@ use std::collections::HashMap;
 
 use dict_derive::FromPyObject;
 
+use crate::error::BacktestError;
+use crate::evaluation::engine_settings::EngineSettings;
+use crate::evaluation::order::TimeInForce;
 use super::entry::entry::Entry;
 use super::entry::bollinger::Bollinger as BollingerEntry;
+use super::entry::breakout::Breakout;
 use super::entry::immediate::Immediate;
 use super::entry::volatility_mean::VolatilityMean;
 use super::entry::volatility_pullback::VolatilityPullback;
@@ -11,8 +15,19 @@ use super::exit::exit::Exit;
 use super::exit::bollinger::Bollinger as BollingerExit;
 use super::exit::fixed_tp::FixedTP;
 use super::exit::trailing_stop::TrailingStop;
+use super::filter::counter_ssi::CounterSsi;
 use super::filter::filter::Filter;
 use super::filter::no_filter::NoFilter;
+use super::scale_out::{ScaleOut, ScaleOutRules};
+use super::sizer::equity_percent::EquityPercent;
+use super::sizer::fixed_fractional::FixedFractional;
+use super::sizer::fixed_lots::FixedLots;
+use super::sizer::sizer::Sizer;
+use super::sizer::volatility_target::VolatilityTarget;
+use super::stop_manager::atr_trailing::AtrTrailing;
+use super::stop_manager::break_even::BreakEven;
+use super::stop_manager::profit_lock::ProfitLock;
+use super::stop_manager::stop_manager::StopManager;
 
 #[derive(Clone, Debug, FromPyObject)]
 struct Rule {
@@ -25,12 +40,22 @@ pub struct StrategyRules {
     entry: Rule,
     exit: Rule,
     filter: Rule,
+    sizer: Option<Rule>,
+    stop_manager: Option<Rule>,
+    entry_timeout_minutes: Option<f32>,
+    time_in_force: Option<String>,
+    scale_out: Option<ScaleOutRules>,
 }
 
 pub struct Strategy {
     pub entry: Box<dyn Entry>,
     pub exit: Box<dyn Exit>,
     pub filter: Box<dyn Filter>,
+    pub sizer: Box<dyn Sizer>,
+    pub stop_manager: Option<Box<dyn StopManager>>,
+    pub entry_timeout_minutes: Option<f32>,
+    pub time_in_force: TimeInForce,
+    pub scale_out: Option<ScaleOut>,
 }
 
 pub struct StrategyManager {
@@ -54,6 +79,14 @@ impl StrategyManager {
                 let period_minutes = rule.parameters.get("period_minutes").ok_or("Missing period_minutes")?;
                 Ok(Box::new(BollingerEntry::new(*std_dev_factor, *period_minutes as i32)))
             }
+            "breakout" => {
+                let entry_factor = rule.parameters.get("entry_factor").ok_or("Missing entry_factor")?;
+                let vol_timeframe = rule.parameters.get("vol_timeframe").ok_or("Missing vol_timeframe")?;
+                if rule.parameters.get("limit_factor").is_some_and(|limit_factor| limit_factor < entry_factor) {
+                    return Err("limit_factor has to be at least entry_factor");
+                }
+                Ok(Box::new(Breakout::new(*entry_factor, rule.parameters.get("limit_factor").copied(), *vol_timeframe)))
+            }
             "immediate" => Ok(Box::new(Immediate::new())),
             _ => Err("Invalid entry strategy name"),
         }
@@ -82,19 +115,136 @@ impl StrategyManager {
     fn map_strategy_filter(rule: &Rule) -> Result<Box<dyn Filter>, &'static str> {
         match rule.name.as_str() {
             "no_filter" => Ok(Box::new(NoFilter::new())),
+            "counter_ssi" => {
+                let threshold_long = rule.parameters.get("threshold_long").ok_or("Missing threshold_long")?;
+                let threshold_short = rule.parameters.get("threshold_short").ok_or("Missing threshold_short")?;
+                Ok(Box::new(CounterSsi::new(*threshold_long, *threshold_short)))
+            }
             _ => Err("Invalid filter strategy name"),
         }
     }
 
-    pub fn convert_rules_to_strategy(rules: &StrategyRules) -> Result<Strategy, &'static str> {
-        let entry = StrategyManager::map_strategy_entry(&rules.entry)?;
-        let exit = StrategyManager::map_strategy_exit(&rules.exit)?;
-        let filter = StrategyManager::map_strategy_filter(&rules.filter)?;
+    fn map_strategy_sizer(rule: &Rule) -> Result<Box<dyn Sizer>, &'static str> {
+        match rule.name.as_str() {
+            "fixed_lots" => Ok(Box::new(FixedLots::new(rule.parameters.get("lots").copied()))),
+            "fixed_fractional" => {
+                let risk_fraction = rule.parameters.get("risk_fraction").ok_or("Missing risk_fraction")?;
+                Ok(Box::new(FixedFractional::new(*risk_fraction)))
+            }
+            "equity_percent" => {
+                let percent = rule.parameters.get("percent").ok_or("Missing percent")?;
+                Ok(Box::new(EquityPercent::new(*percent)))
+            }
+            "volatility_target" => {
+                let target_volatility = rule.parameters.get("target_volatility").ok_or("Missing target_volatility")?;
+                let vol_timeframe = rule.parameters.get("vol_timeframe").ok_or("Missing vol_timeframe")?;
+                Ok(Box::new(VolatilityTarget::new(*target_volatility, *vol_timeframe)))
+            }
+            _ => Err("Invalid sizer strategy name"),
+        }
+    }
+
+    fn map_strategy_stop_manager(rule: &Rule) -> Result<Box<dyn StopManager>, &'static str> {
+        match rule.name.as_str() {
+            "break_even" => {
+                let trigger_r = rule.parameters.get("trigger_r").ok_or("Missing trigger_r")?;
+                Ok(Box::new(BreakEven::new(*trigger_r, rule.parameters.get("offset_r").copied().unwrap_or(0.0))))
+            }
+            "profit_lock" => {
+                let step_r = rule.parameters.get("step_r").ok_or("Missing step_r")?;
+                let lock_fraction = rule.parameters.get("lock_fraction").ok_or("Missing lock_fraction")?;
+                Ok(Box::new(ProfitLock::new(*step_r, *lock_fraction)))
+            }
+            "atr_trailing" => {
+                let atr_factor = rule.parameters.get("atr_factor").ok_or("Missing atr_factor")?;
+                let period_minutes = rule.parameters.get("period_minutes").ok_or("Missing period_minutes")?;
+                Ok(Box::new(AtrTrailing::new(*atr_factor, *period_minutes)))
+            }
+            _ => Err("Invalid stop manager name"),
+        }
+    }
+
+    pub fn convert_rules_to_strategy(rules: &StrategyRules) -> Result<Strategy, BacktestError> {
+        let config_error = |rule: &Rule, err: &str| BacktestError::StrategyConfig(format!("{} for rule '{}'", err, rule.name));
+        let entry = StrategyManager::map_strategy_entry(&rules.entry).map_err(|err| config_error(&rules.entry, err))?;
+        let exit = StrategyManager::map_strategy_exit(&rules.exit).map_err(|err| config_error(&rules.exit, err))?;
+        let filter = StrategyManager::map_strategy_filter(&rules.filter).map_err(|err| config_error(&rules.filter, err))?;
+        let sizer = match &rules.sizer {
+            Some(rule) => StrategyManager::map_strategy_sizer(rule).map_err(|err| config_error(rule, err))?,
+            None => Box::new(FixedLots::new(None)),
+        };
+        let stop_manager = match &rules.stop_manager {
+            Some(rule) => Some(StrategyManager::map_strategy_stop_manager(rule).map_err(|err| config_error(rule, err))?),
+            None => None,
+        };
+        EngineSettings::validate_entry_timeout(rules.entry_timeout_minutes).map_err(|err| BacktestError::StrategyConfig(err.to_string()))?;
+        let time_in_force = match &rules.time_in_force {
+            Some(time_in_force) => TimeInForce::parse(time_in_force).map_err(BacktestError::StrategyConfig)?,
+            None => TimeInForce::GoodTillCancelled,
+        };
+        let scale_out = rules.scale_out.as_ref().map(ScaleOut::new).transpose().map_err(BacktestError::StrategyConfig)?;
 
         Ok(Strategy {
             entry,
             exit,
             filter,
+            sizer,
+            stop_manager,
+            entry_timeout_minutes: rules.entry_timeout_minutes,
+            time_in_force,
+            scale_out,
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+impl Rule {
+    fn new(name: &str, parameters: &[(&str, f32)]) -> Self {
+        Rule {
+            name: name.to_string(),
+            parameters: parameters.iter().map(|&(key, value)| (key.to_string(), value)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl StrategyRules {
+    /// Rules with the given entry and exit, no filter and the defaults for everything else.
+    pub fn new(entry: &str, entry_parameters: &[(&str, f32)], exit: &str, exit_parameters: &[(&str, f32)]) -> Self {
+        StrategyRules {
+            entry: Rule::new(entry, entry_parameters),
+            exit: Rule::new(exit, exit_parameters),
+            filter: Rule::new("no_filter", &[]),
+            sizer: None,
+            stop_manager: None,
+            entry_timeout_minutes: None,
+            time_in_force: None,
+            scale_out: None,
+        }
+    }
+
+    pub fn with_sizer(mut self, name: &str, parameters: &[(&str, f32)]) -> Self {
+        self.sizer = Some(Rule::new(name, parameters));
+        self
+    }
+
+    pub fn with_entry_timeout(mut self, entry_timeout_minutes: f32) -> Self {
+        self.entry_timeout_minutes = Some(entry_timeout_minutes);
+        self
+    }
+
+    pub fn with_scale_out(mut self, scale_out: ScaleOutRules) -> Self {
+        self.scale_out = Some(scale_out);
+        self
+    }
+
+    pub fn with_stop_manager(mut self, name: &str, parameters: &[(&str, f32)]) -> Self {
+        self.stop_manager = Some(Rule::new(name, parameters));
+        self
+    }
+
+    pub fn with_time_in_force(mut self, time_in_force: &str) -> Self {
+        self.time_in_force = Some(time_in_force.to_string());
+        self
+    }
+}